@@ -1,26 +1,192 @@
-use crate::domain::{Applicant, PremiumResult};
-use crate::repository::{ApplicantRepository, FormulaRepository};
-use crate::services::PremiumCalculationService;
+use crate::domain::{
+    Applicant, CalculationFailure, FormulaCase, PremiumResult, Rejection, RowError,
+};
+use crate::export::ResultWriter;
+use crate::repository::{
+    check_error_budget, ApplicantRepository, BaseRateTable, FormulaRepository, LoadReport,
+    OccupationRiskTable,
+};
+use crate::services::{
+    CaseReport, CaseRunner, ModalFactors, PremiumCalculationService, PremiumComparison,
+    UnderwritingRules, ValidationReport, ValidationRules,
+};
 use rayon::prelude::*;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::path::Path;
 use std::time::{Duration, Instant};
 
+/// Rows a stream must have read before the error budget can stop it early
+/// Below this a few bad rows near the top of a file would take the rate over any budget
+pub const MIN_BUDGET_ROWS: usize = 1000;
+
+/// Outcome of rating a batch of applicants
+pub struct CalculationBatch {
+    pub results: Vec<PremiumResult>,
+    pub failures: Vec<CalculationFailure>,
+    pub duration: Duration,
+    /// Sub-factors of the formula set that rated the batch, mapped to the factor they feed
+    pub sub_factors: BTreeMap<String, String>,
+}
+
+/// Outcome of rating the same applicants with the current and a candidate formula set
+pub struct ComparisonBatch {
+    pub baseline: CalculationBatch,
+    pub candidate: CalculationBatch,
+    pub comparison: PremiumComparison,
+}
+
+/// Number of diagnostics of each kind a stream keeps as examples
+pub const STREAM_SAMPLE_SIZE: usize = 5;
+
+/// How many diagnostics of one kind a stream saw, with the first few kept as examples
+#[derive(Debug, Clone)]
+pub struct DiagnosticSample<T> {
+    pub count: usize,
+    pub first: Vec<T>,
+}
+
+impl<T> Default for DiagnosticSample<T> {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            first: Vec::new(),
+        }
+    }
+}
+
+impl<T> DiagnosticSample<T> {
+    fn push(&mut self, item: T) {
+        self.count += 1;
+        if self.first.len() < STREAM_SAMPLE_SIZE {
+            self.first.push(item);
+        }
+    }
+}
+
+/// Outcome of streaming applicants through the calculation
+/// Only counts and a sample of the diagnostics are kept; results go straight to the writer
+#[derive(Debug, Default)]
+pub struct StreamSummary {
+    pub rows_read: usize,
+    pub calculated: usize,
+    pub skipped: DiagnosticSample<RowError>,
+    pub rejected: DiagnosticSample<Rejection>,
+    /// Number of rejected records breaking each rule; a record can break several rules
+    pub rejected_by_rule: BTreeMap<String, usize>,
+    pub failures: DiagnosticSample<CalculationFailure>,
+    pub duration: Duration,
+}
+
 /// Application service orchestrating the premium calculation workflow
 /// Follows Single Responsibility Principle - coordinates use cases
 pub struct PremiumCalculationApp {
     repository: Box<dyn ApplicantRepository + Send + Sync>,
     calculator: PremiumCalculationService,
+    validation: ValidationRules,
+    strict: bool,
 }
 
 impl PremiumCalculationApp {
     pub fn new(
         repository: Box<dyn ApplicantRepository + Send + Sync>,
         formula_repository: Box<dyn FormulaRepository>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
             repository,
-            calculator: PremiumCalculationService::new(formula_repository),
-        }
+            calculator: PremiumCalculationService::new(formula_repository)?,
+            validation: ValidationRules::default(),
+            strict: false,
+        })
+    }
+
+    /// In strict mode a batch aborts on the first applicant that fails
+    pub fn with_strict_mode(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Rate occupations with the given risk table instead of the built-in one
+    pub fn with_occupation_table(mut self, table: OccupationRiskTable) -> Self {
+        self.calculator = self.calculator.with_occupation_table(table);
+        self
+    }
+
+    /// Look up base rates in the given table instead of the built-in one
+    pub fn with_base_rate_table(mut self, table: BaseRateTable) -> Self {
+        self.calculator = self.calculator.with_base_rate_table(table);
+        self
+    }
+
+    /// Decide on applicants with the given underwriting rules instead of the built-in ones
+    /// Fails when a rated output is not one of the loaded formulas
+    pub fn with_underwriting_rules(
+        mut self,
+        rules: UnderwritingRules,
+    ) -> Result<Self, Box<dyn Error>> {
+        self.calculator = self.calculator.with_underwriting_rules(rules)?;
+        Ok(self)
+    }
+
+    /// Bill payment modes with the given modal factors instead of the built-in ones
+    pub fn with_modal_factors(mut self, factors: ModalFactors) -> Self {
+        self.calculator = self.calculator.with_modal_factors(factors);
+        self
+    }
+
+    /// Record on every result how each formula reached its output
+    pub fn with_explain(mut self, explain: bool) -> Self {
+        self.calculator = self.calculator.with_explain(explain);
+        self
+    }
+
+    /// Validate applicants with the given rules instead of the built-in ones
+    pub fn with_validation_rules(mut self, rules: ValidationRules) -> Self {
+        self.validation = rules;
+        self
+    }
+
+    /// Names of the formulas in the compiled rating plan
+    pub fn formula_names(&self) -> Vec<String> {
+        self.calculator.formula_names()
+    }
+
+    /// Variables set for every applicant that no formula reads
+    pub fn unused_variables(&self) -> &[String] {
+        self.calculator.unused_variables()
+    }
+
+    /// Formulas that no result formula depends on
+    pub fn unused_formulas(&self) -> &[String] {
+        self.calculator.unused_formulas()
+    }
+
+    /// Formulas the final premium only reads through another formula, mapped to that formula
+    pub fn sub_factors(&self) -> &BTreeMap<String, String> {
+        self.calculator.sub_factors()
+    }
+
+    /// Directory the formula scripts were loaded from; `None` for the built-in set
+    pub fn formula_directory(&self) -> Option<&Path> {
+        self.calculator.formula_directory()
+    }
+
+    /// Read and compile the formula scripts again and rate the applicants with them
+    /// The new formulas are only put in use once the batch succeeds; on any error, including
+    /// a failed applicant in strict mode, the previous formulas stay in use
+    pub fn reload_formulas(
+        &mut self,
+        applicants: &[Applicant],
+    ) -> Result<CalculationBatch, Box<dyn Error>> {
+        let candidate = self.calculator.reloaded()?;
+        let batch = self.calculate_with(&candidate, applicants)?;
+        self.calculator = candidate;
+        Ok(batch)
+    }
+
+    /// Check the loaded formulas against golden test cases
+    pub fn run_formula_cases(&self, cases: &[FormulaCase]) -> CaseReport {
+        CaseRunner::new(&self.calculator).run(cases)
     }
 
     /// Load applicants from repository
@@ -28,37 +194,215 @@ impl PremiumCalculationApp {
         self.repository.load_all()
     }
 
+    /// Load applicants, skipping rows that cannot be parsed and reporting them
+    pub fn load_applicants_lenient(&self) -> Result<LoadReport, Box<dyn Error>> {
+        self.repository.load_lenient()
+    }
+
+    /// Separate applicants that pass the validation rules from rejected ones
+    pub fn validate_applicants(&self, applicants: Vec<Applicant>) -> ValidationReport {
+        self.validation.split(applicants)
+    }
+
     /// Calculate premiums for all applicants in parallel
+    /// Failed applicants are reported alongside the results, or abort the batch in strict mode
     pub fn calculate_all_premiums(
         &self,
         applicants: Vec<Applicant>,
+    ) -> Result<CalculationBatch, CalculationFailure> {
+        self.calculate_with(&self.calculator, &applicants)
+    }
+
+    /// Rate the applicants with the current formula set and with `candidate`, and pair up
+    /// the premiums to show the impact of a rate change
+    /// Tables and rules are shared, so only the formulas differ between the two runs
+    pub fn compare_premiums(
+        &self,
+        candidate: Box<dyn FormulaRepository>,
+        applicants: Vec<Applicant>,
+    ) -> Result<ComparisonBatch, Box<dyn Error>> {
+        let candidate_calculator = self.calculator.rebuild(candidate)?;
+        let baseline = self.calculate_with(&self.calculator, &applicants)?;
+        let candidate = self.calculate_with(&candidate_calculator, &applicants)?;
+
+        let mut comparison = PremiumComparison::new(&baseline.results, &candidate.results);
+        comparison.baseline_version = self.calculator.plan_version().to_string();
+        comparison.candidate_version = candidate_calculator.plan_version().to_string();
+        Ok(ComparisonBatch {
+            baseline,
+            candidate,
+            comparison,
+        })
+    }
+
+    fn calculate_with(
+        &self,
+        calculator: &PremiumCalculationService,
+        applicants: &[Applicant],
+    ) -> Result<CalculationBatch, CalculationFailure> {
+        let start = Instant::now();
+
+        let (results, failures) = if self.strict {
+            let results = applicants
+                .par_iter()
+                .map(|applicant| rate(calculator, applicant))
+                .collect::<Result<Vec<_>, _>>()?;
+            (results, Vec::new())
+        } else {
+            let outcomes: Vec<Result<PremiumResult, CalculationFailure>> = applicants
+                .par_iter()
+                .map(|applicant| rate(calculator, applicant))
+                .collect();
+
+            let mut results = Vec::with_capacity(outcomes.len());
+            let mut failures = Vec::new();
+            for outcome in outcomes {
+                match outcome {
+                    Ok(result) => results.push(result),
+                    Err(failure) => failures.push(failure),
+                }
+            }
+            (results, failures)
+        };
+
+        Ok(CalculationBatch {
+            results,
+            failures,
+            duration: start.elapsed(),
+            sub_factors: calculator.sub_factors().clone(),
+        })
+    }
+
+    /// Read, validate, rate and write applicants in chunks of `chunk_size` rows
+    /// Memory stays bounded by the chunk size and results are written in input order.
+    /// In lenient mode unparseable rows are skipped and reported instead of failing the run;
+    /// with `max_error_rate` the run stops at the first chunk that takes the rows read so far
+    /// over that budget once `MIN_BUDGET_ROWS` have been read, and otherwise fails at the end
+    /// of the file. The writer is finished on every path, so the output stays well-formed
+    pub fn stream_premiums(
+        &self,
+        chunk_size: usize,
+        lenient: bool,
+        max_error_rate: Option<f64>,
+        writer: &mut dyn ResultWriter,
+    ) -> Result<StreamSummary, Box<dyn Error>> {
+        let start = Instant::now();
+        let mut summary = StreamSummary::default();
+        let streamed = self.stream_chunks(
+            chunk_size.max(1),
+            lenient,
+            max_error_rate,
+            writer,
+            &mut summary,
+        );
+        let finished = writer.finish();
+        streamed?;
+        finished?;
+
+        summary.duration = start.elapsed();
+        Ok(summary)
+    }
+
+    fn stream_chunks(
+        &self,
+        chunk_size: usize,
+        lenient: bool,
+        max_error_rate: Option<f64>,
+        writer: &mut dyn ResultWriter,
+        summary: &mut StreamSummary,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rows = self.repository.rows()?;
+
+        loop {
+            let rows_before = summary.rows_read;
+            let mut chunk = Vec::with_capacity(chunk_size);
+            for row in rows.by_ref().take(chunk_size) {
+                summary.rows_read += 1;
+                match row? {
+                    Ok(applicant) => match self.validation.validate(&applicant) {
+                        Ok(()) => chunk.push(applicant),
+                        Err(rejection) => {
+                            for violation in &rejection.violations {
+                                *summary
+                                    .rejected_by_rule
+                                    .entry(violation.rule.clone())
+                                    .or_insert(0) += 1;
+                            }
+                            summary.rejected.push(rejection);
+                        }
+                    },
+                    Err(row_error) if lenient => summary.skipped.push(row_error),
+                    Err(row_error) => return Err(row_error.into()),
+                }
+            }
+            let finished = summary.rows_read == rows_before;
+            if let Some(max_error_rate) = max_error_rate {
+                if finished || summary.rows_read >= MIN_BUDGET_ROWS {
+                    check_error_budget(summary.skipped.count, summary.rows_read, max_error_rate)?;
+                }
+            }
+            if finished {
+                return Ok(());
+            }
+
+            let batch = self.calculate_all_premiums(chunk)?;
+            for result in &batch.results {
+                writer.write_result(result)?;
+            }
+            summary.calculated += batch.results.len();
+            for failure in batch.failures {
+                summary.failures.push(failure);
+            }
+        }
+    }
+
+    /// Calculate premiums recompiling the formulas for every applicant
+    /// Used by benchmark mode as the baseline for the compiled rating plan; only timed,
+    /// so failed applicants are not collected
+    pub fn calculate_all_premiums_uncompiled(
+        &self,
+        applicants: &[Applicant],
     ) -> (Vec<PremiumResult>, Duration) {
         let start = Instant::now();
 
         let results: Vec<PremiumResult> = applicants
             .par_iter()
-            .filter_map(|applicant| self.calculator.calculate(applicant).ok())
+            .filter_map(|applicant| self.calculator.calculate_uncompiled(applicant).ok())
             .collect();
 
         let duration = start.elapsed();
         (results, duration)
     }
 
-    /// Calculate premium for a single applicant
-    #[allow(dead_code)]
+    /// Validate and calculate premium for a single applicant
     pub fn calculate_premium(
         &self,
         applicant: &Applicant,
     ) -> Result<PremiumResult, Box<dyn Error>> {
-        self.calculator.calculate(applicant)
+        self.validation.validate(applicant)?;
+        Ok(self.calculator.calculate(applicant)?)
     }
 }
 
+fn rate(
+    calculator: &PremiumCalculationService,
+    applicant: &Applicant,
+) -> Result<PremiumResult, CalculationFailure> {
+    calculator
+        .calculate(applicant)
+        .map_err(|e| CalculationFailure {
+            applicant_id: applicant.id,
+            formula: e.formula,
+            message: e.message,
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::Applicant;
-    use crate::repository::InMemoryFormulaRepository;
+    use crate::domain::{Applicant, Gender, SmokerStatus};
+    use crate::repository::{FileFormulaRepository, InMemoryFormulaRepository};
+    use formcalc::{Formula, FormulaT};
     use std::error::Error;
 
     struct MockRepository {
@@ -82,25 +426,52 @@ mod tests {
             id,
             name: format!("Test User {}", id),
             age,
-            gender: "M".to_string(),
-            smoker: false,
+            gender: Gender::Male,
+            smoker: SmokerStatus::NonSmoker,
             occupation: "Engineer".to_string(),
             annual_income: 75000.0,
             bmi: 22.5,
             blood_pressure_sys: 120,
             blood_pressure_dia: 80,
             cholesterol: 180,
-            existing_conditions: "none".to_string(),
+            existing_conditions: Vec::new(),
             family_history_score: 2,
             coverage_amount: 500000.0,
             coverage_years: 20,
         }
     }
 
+    /// Formula set whose final premium fails for applicants aged 60 or over
+    struct AgeLimitedFormulaRepository;
+
+    impl FormulaRepository for AgeLimitedFormulaRepository {
+        fn load_all(&self) -> Result<Vec<Formula>, Box<dyn Error>> {
+            Ok(InMemoryFormulaRepository::new()
+                .load_all()?
+                .into_iter()
+                .map(|formula| {
+                    if formula.name() == "final_premium" {
+                        Formula::new(
+                            "final_premium",
+                            "if (age < 60) then return 100 else error('too old') end",
+                        )
+                    } else {
+                        formula
+                    }
+                })
+                .collect())
+        }
+    }
+
+    fn create_age_limited_app(applicants: Vec<Applicant>) -> PremiumCalculationApp {
+        let repository = Box::new(MockRepository::new(applicants));
+        PremiumCalculationApp::new(repository, Box::new(AgeLimitedFormulaRepository)).unwrap()
+    }
+
     fn create_app(applicants: Vec<Applicant>) -> PremiumCalculationApp {
         let repository = Box::new(MockRepository::new(applicants));
         let formula_repository = Box::new(InMemoryFormulaRepository::new());
-        PremiumCalculationApp::new(repository, formula_repository)
+        PremiumCalculationApp::new(repository, formula_repository).unwrap()
     }
 
     #[test]
@@ -124,6 +495,73 @@ mod tests {
         assert!(result.final_premium > 0.0);
     }
 
+    #[test]
+    fn test_calculate_premium_rejects_invalid_applicant() {
+        let mut applicant = create_test_applicant(1, 30);
+        applicant.bmi = 400.0;
+        let app = create_app(vec![]);
+
+        let err = app.calculate_premium(&applicant).err().unwrap();
+        assert!(err.to_string().contains("bmi"));
+    }
+
+    #[test]
+    fn test_validate_applicants() {
+        let mut invalid = create_test_applicant(2, 30);
+        invalid.bmi = 400.0;
+        let app = create_app(vec![]);
+
+        let report = app.validate_applicants(vec![create_test_applicant(1, 30), invalid]);
+        assert_eq!(report.valid.len(), 1);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].applicant_id, 2);
+    }
+
+    #[test]
+    fn test_compare_premiums_with_candidate_formulas() {
+        let applicants = vec![create_test_applicant(1, 30), create_test_applicant(2, 65)];
+        let app = create_app(applicants.clone());
+
+        let batch = app
+            .compare_premiums(Box::new(AgeLimitedFormulaRepository), applicants)
+            .unwrap();
+        assert_eq!(batch.baseline.results.len(), 2);
+        assert_eq!(batch.candidate.failures.len(), 1);
+
+        let comparison = batch.comparison;
+        assert_eq!(comparison.baseline_version, "built-in");
+        assert_eq!(comparison.candidate_version, "unversioned");
+        assert_eq!(comparison.changes.len(), 1);
+        assert_eq!(comparison.changes[0].candidate, 100.0);
+        assert_eq!(
+            comparison.changes[0].baseline,
+            batch.baseline.results[0].final_premium
+        );
+        assert_eq!(comparison.baseline_only, vec![2]);
+    }
+
+    #[test]
+    fn test_compare_rejects_candidate_missing_a_rated_output() {
+        let dir = crate::test_support::temp_dir("compare_rated_output");
+        crate::test_support::write_formulas(&dir, "return 100");
+        std::fs::remove_file(dir.join("health_risk_score.fc")).unwrap();
+        let applicants = vec![create_test_applicant(1, 30)];
+        let app = create_app(applicants.clone());
+
+        let err = app
+            .compare_premiums(Box::new(FileFormulaRepository::new(&dir)), applicants)
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string()
+                .contains("rated_outputs names unknown formulas: health_risk_score"),
+            "{}",
+            err
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
     #[test]
     fn test_calculate_all_premiums() {
         let applicants = vec![
@@ -133,15 +571,200 @@ mod tests {
         ];
         let app = create_app(applicants.clone());
 
-        let (results, duration) = app.calculate_all_premiums(applicants);
+        let batch = app.calculate_all_premiums(applicants).unwrap();
+        let results = batch.results;
 
         assert_eq!(results.len(), 3);
-        assert!(duration.as_nanos() > 0);
+        assert!(batch.failures.is_empty());
+        assert!(batch.duration.as_nanos() > 0);
         assert_eq!(results[0].applicant.id, 1);
         assert_eq!(results[1].applicant.id, 2);
         assert_eq!(results[2].applicant.id, 3);
     }
 
+    /// Collects the ids written, in order
+    struct IdWriter {
+        ids: Vec<u32>,
+        finished: bool,
+    }
+
+    impl ResultWriter for IdWriter {
+        fn write_result(&mut self, result: &PremiumResult) -> Result<(), Box<dyn Error>> {
+            self.ids.push(result.applicant.id);
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+            self.finished = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stream_premiums_preserves_order_across_chunks() {
+        let mut applicants: Vec<Applicant> = (1..=10)
+            .map(|id| create_test_applicant(id, 30 + id))
+            .collect();
+        applicants[3].bmi = 400.0;
+        let app = create_age_limited_app(applicants);
+        let mut writer = IdWriter {
+            ids: Vec::new(),
+            finished: false,
+        };
+
+        let summary = app.stream_premiums(3, false, None, &mut writer).unwrap();
+
+        assert!(writer.finished);
+        assert_eq!(writer.ids, vec![1, 2, 3, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(summary.rows_read, 10);
+        assert_eq!(summary.calculated, 9);
+        assert_eq!(summary.rejected.count, 1);
+        assert_eq!(summary.rejected.first[0].applicant_id, 4);
+        assert_eq!(summary.rejected_by_rule.get("bmi"), Some(&1));
+        assert_eq!(summary.failures.count, 0);
+    }
+
+    #[test]
+    fn test_stream_premiums_reports_failures() {
+        let applicants = vec![create_test_applicant(1, 30), create_test_applicant(2, 65)];
+        let app = create_age_limited_app(applicants);
+        let mut writer = IdWriter {
+            ids: Vec::new(),
+            finished: false,
+        };
+
+        let summary = app.stream_premiums(1, false, None, &mut writer).unwrap();
+        assert_eq!(writer.ids, vec![1]);
+        assert_eq!(summary.failures.first[0].applicant_id, 2);
+    }
+
+    #[test]
+    fn test_stream_premiums_keeps_a_sample_of_diagnostics() {
+        let applicants = (1..=20)
+            .map(|id| create_test_applicant(id, 60 + id))
+            .collect();
+        let app = create_age_limited_app(applicants);
+        let mut writer = IdWriter {
+            ids: Vec::new(),
+            finished: false,
+        };
+
+        let summary = app.stream_premiums(4, false, None, &mut writer).unwrap();
+        assert_eq!(summary.failures.count, 20);
+        assert_eq!(summary.failures.first.len(), STREAM_SAMPLE_SIZE);
+        assert_eq!(summary.failures.first[0].applicant_id, 1);
+    }
+
+    #[test]
+    fn test_stream_premiums_finishes_writer_when_aborted() {
+        let applicants = vec![create_test_applicant(1, 30), create_test_applicant(2, 65)];
+        let app = create_age_limited_app(applicants).with_strict_mode(true);
+        let mut writer = IdWriter {
+            ids: Vec::new(),
+            finished: false,
+        };
+
+        assert!(app.stream_premiums(1, false, None, &mut writer).is_err());
+        assert_eq!(writer.ids, vec![1]);
+        assert!(writer.finished);
+    }
+
+    /// Applicants 1 to `rows` where the ids picked by `broken` cannot be parsed
+    struct BrokenRowsRepository {
+        rows: u32,
+        broken: fn(u32) -> bool,
+    }
+
+    impl ApplicantRepository for BrokenRowsRepository {
+        fn load_all(&self) -> Result<Vec<Applicant>, Box<dyn Error>> {
+            Err("not used".into())
+        }
+
+        fn rows(&self) -> Result<crate::repository::ApplicantRows<'_>, Box<dyn Error>> {
+            Ok(Box::new((1..=self.rows).map(|id| {
+                Ok(if (self.broken)(id) {
+                    Err(RowError {
+                        line: id as u64 + 1,
+                        column: Some("age".to_string()),
+                        raw: "x".to_string(),
+                        message: "invalid digit found in string".to_string(),
+                    })
+                } else {
+                    Ok(create_test_applicant(id, 30))
+                })
+            })))
+        }
+    }
+
+    fn broken_rows_app(rows: u32, broken: fn(u32) -> bool) -> PremiumCalculationApp {
+        PremiumCalculationApp::new(
+            Box::new(BrokenRowsRepository { rows, broken }),
+            Box::new(InMemoryFormulaRepository::new()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stream_premiums_checks_short_files_at_the_end() {
+        let app = broken_rows_app(10, |id| id % 2 == 0);
+        let mut writer = IdWriter {
+            ids: Vec::new(),
+            finished: false,
+        };
+
+        let err = app
+            .stream_premiums(4, true, Some(0.1), &mut writer)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("5 of 10 rows"), "{}", err);
+        assert_eq!(writer.ids, vec![1, 3, 5, 7, 9]);
+        assert!(writer.finished);
+
+        let mut writer = IdWriter {
+            ids: Vec::new(),
+            finished: false,
+        };
+        let summary = app
+            .stream_premiums(4, true, Some(0.5), &mut writer)
+            .unwrap();
+        assert_eq!(writer.ids, vec![1, 3, 5, 7, 9]);
+        assert_eq!(summary.skipped.count, 5);
+    }
+
+    #[test]
+    fn test_stream_premiums_stops_at_error_budget() {
+        let app = broken_rows_app(2 * MIN_BUDGET_ROWS as u32, |id| id % 2 == 0);
+        let mut writer = IdWriter {
+            ids: Vec::new(),
+            finished: false,
+        };
+
+        let err = app
+            .stream_premiums(MIN_BUDGET_ROWS / 2, true, Some(0.1), &mut writer)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("error budget"), "{}", err);
+        // The first chunk is rated before enough rows are read to enforce the budget
+        assert_eq!(writer.ids.len(), MIN_BUDGET_ROWS / 4);
+        assert!(writer.finished);
+    }
+
+    #[test]
+    fn test_stream_premiums_tolerates_early_bad_rows() {
+        // Every bad row is in the first chunk, but the file as a whole is within 1%
+        let app = broken_rows_app(2 * MIN_BUDGET_ROWS as u32, |id| id <= 10);
+        let mut writer = IdWriter {
+            ids: Vec::new(),
+            finished: false,
+        };
+
+        let summary = app
+            .stream_premiums(100, true, Some(0.01), &mut writer)
+            .unwrap();
+        assert_eq!(summary.skipped.count, 10);
+        assert_eq!(writer.ids.len(), 2 * MIN_BUDGET_ROWS - 10);
+    }
+
     #[test]
     fn test_parallel_calculation_performance() {
         let applicants: Vec<Applicant> = (1..=100)
@@ -150,7 +773,78 @@ mod tests {
 
         let app = create_app(applicants.clone());
 
-        let (results, _duration) = app.calculate_all_premiums(applicants);
-        assert_eq!(results.len(), 100);
+        let batch = app.calculate_all_premiums(applicants).unwrap();
+        assert_eq!(batch.results.len(), 100);
+    }
+
+    #[test]
+    fn test_uncompiled_baseline_matches() {
+        let applicants = vec![create_test_applicant(1, 30), create_test_applicant(2, 55)];
+        let app = create_app(applicants.clone());
+
+        let (baseline, _) = app.calculate_all_premiums_uncompiled(&applicants);
+        let results = app.calculate_all_premiums(applicants).unwrap().results;
+
+        assert_eq!(baseline.len(), results.len());
+        for (old, new) in baseline.iter().zip(&results) {
+            assert_eq!(old.final_premium, new.final_premium);
+        }
+    }
+
+    #[test]
+    fn test_failures_are_reported_not_dropped() {
+        let applicants = vec![
+            create_test_applicant(1, 30),
+            create_test_applicant(2, 65),
+            create_test_applicant(3, 40),
+        ];
+        let app = create_age_limited_app(applicants.clone());
+
+        let batch = app.calculate_all_premiums(applicants).unwrap();
+        assert_eq!(batch.results.len(), 2);
+        assert_eq!(batch.failures.len(), 1);
+        assert_eq!(batch.failures[0].applicant_id, 2);
+        assert_eq!(batch.failures[0].formula, "final_premium");
+        assert!(batch.failures[0].message.contains("too old"));
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_previous_formulas() {
+        let dir = crate::test_support::temp_dir("app_reload");
+        crate::test_support::write_formulas(&dir, "return 120");
+
+        let applicants = vec![create_test_applicant(1, 30), create_test_applicant(2, 65)];
+        let repository = Box::new(MockRepository::new(applicants.clone()));
+        let mut app =
+            PremiumCalculationApp::new(repository, Box::new(FileFormulaRepository::new(&dir)))
+                .unwrap()
+                .with_strict_mode(true);
+
+        // Compiles, but fails the second applicant, which aborts the batch in strict mode
+        std::fs::write(
+            dir.join("final_premium.fc"),
+            "if (age < 60) then return 150 else error('too old') end",
+        )
+        .unwrap();
+        assert!(app.reload_formulas(&applicants).is_err());
+        let batch = app.calculate_all_premiums(applicants.clone()).unwrap();
+        assert_eq!(batch.results[1].final_premium, 120.0);
+
+        std::fs::write(dir.join("final_premium.fc"), "return 150").unwrap();
+        let batch = app.reload_formulas(&applicants).unwrap();
+        assert_eq!(batch.results[1].final_premium, 150.0);
+        let batch = app.calculate_all_premiums(applicants).unwrap();
+        assert_eq!(batch.results[1].final_premium, 150.0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_strict_mode_aborts_on_failure() {
+        let applicants = vec![create_test_applicant(1, 30), create_test_applicant(2, 65)];
+        let app = create_age_limited_app(applicants.clone()).with_strict_mode(true);
+
+        let failure = app.calculate_all_premiums(applicants).err().unwrap();
+        assert_eq!(failure.applicant_id, 2);
     }
 }
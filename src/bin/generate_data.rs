@@ -221,6 +221,18 @@ fn generate_applicant(id: u32, rng: &mut impl Rng) -> String {
         CONDITIONS[dist.sample(rng)]
     };
 
+    // Older applicants with a condition sometimes have a second one
+    let existing_conditions = if existing_conditions != "none" && age > 55 && rng.gen_bool(0.3) {
+        let second_condition = CONDITIONS[rng.gen_range(1..CONDITIONS.len())];
+        if second_condition != existing_conditions {
+            format!("{};{}", existing_conditions, second_condition)
+        } else {
+            existing_conditions.to_string()
+        }
+    } else {
+        existing_conditions.to_string()
+    };
+
     // Family history score
     let weights = [20, 20, 15, 12, 10, 8, 5, 2];
     let dist = WeightedIndex::new(weights).unwrap();
@@ -0,0 +1,125 @@
+use clap::Parser;
+use log::info;
+use premiumcalc::repository::{
+    BaseRateTable, FileFormulaRepository, FormulaRepository, InMemoryFormulaRepository,
+    OccupationRiskTable, PlanCatalog,
+};
+use premiumcalc::server::QuoteServer;
+use premiumcalc::services::{
+    ModalFactors, PremiumCalculationService, UnderwritingRules, ValidationRules,
+};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Local HTTP quoting service
+#[derive(Parser, Debug)]
+#[command(name = "premiumcalc-server", version)]
+struct Args {
+    /// Port to listen on; the server only binds to 127.0.0.1
+    #[arg(short, long, default_value_t = 8080)]
+    port: u16,
+
+    /// Directory of .fc formula scripts [default: built-in formulas]
+    #[arg(long)]
+    formulas: Option<PathBuf>,
+
+    /// Directory of versioned formula sets; each request is rated with the version in force on
+    /// its quote_date query parameter, today when it is absent
+    #[arg(long, value_name = "DIR", conflicts_with = "formulas")]
+    plans: Option<PathBuf>,
+
+    /// With --plans, serve this version instead of the one effective today
+    #[arg(long, value_name = "ID", requires = "plans")]
+    plan_version: Option<String>,
+
+    /// Occupation risk table, a CSV with occupation,risk_class columns [default: built-in table]
+    #[arg(long)]
+    occupations: Option<PathBuf>,
+
+    /// Base rate table, a CSV with age_min,age_max,gender,smoker,term_min,term_max,rate columns [default: built-in table]
+    #[arg(long)]
+    base_rates: Option<PathBuf>,
+
+    /// Underwriting rules as JSON, same shape as data/underwriting_rules.json [default: built-in rules]
+    #[arg(long)]
+    underwriting_rules: Option<PathBuf>,
+
+    /// Validation rules as JSON, same shape as data/validation_rules.json [default: built-in rules]
+    #[arg(long)]
+    validation_rules: Option<PathBuf>,
+
+    /// Modal factors per payment mode as JSON, same shape as data/modal_factors.json [default: built-in factors]
+    #[arg(long)]
+    modal_factors: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Info)
+        .parse_default_env()
+        .format_target(false)
+        .init();
+
+    let args = Args::parse();
+
+    let mut catalog = match &args.plans {
+        Some(dir) => Some(PlanCatalog::open(dir)?),
+        None => None,
+    };
+    let formula_repository: Box<dyn FormulaRepository> = match (&catalog, &args.formulas) {
+        (Some(plans), _) => {
+            let version = match &args.plan_version {
+                Some(id) => plans.get(id)?,
+                None => plans.versions().last().ok_or("no plan versions listed")?,
+            };
+            Box::new(plans.repository(version))
+        }
+        (None, Some(dir)) => Box::new(FileFormulaRepository::new(dir)),
+        (None, None) => Box::new(InMemoryFormulaRepository::new()),
+    };
+    if args.plan_version.is_some() {
+        catalog = None;
+    }
+    let occupation_table = match &args.occupations {
+        Some(path) => OccupationRiskTable::from_csv(&path.to_string_lossy())?,
+        None => OccupationRiskTable::default(),
+    };
+    let base_rate_table = match &args.base_rates {
+        Some(path) => BaseRateTable::from_csv(&path.to_string_lossy())?,
+        None => BaseRateTable::default(),
+    };
+    let underwriting_rules = match &args.underwriting_rules {
+        Some(path) => UnderwritingRules::from_json(&path.to_string_lossy())?,
+        None => UnderwritingRules::default(),
+    };
+    let validation_rules = match &args.validation_rules {
+        Some(path) => ValidationRules::from_json(&path.to_string_lossy())?,
+        None => ValidationRules::default(),
+    };
+    let modal_factors = match &args.modal_factors {
+        Some(path) => ModalFactors::from_json(&path.to_string_lossy())?,
+        None => ModalFactors::default(),
+    };
+    let calculator = PremiumCalculationService::new(formula_repository)?
+        .with_occupation_table(occupation_table)
+        .with_base_rate_table(base_rate_table)
+        .with_underwriting_rules(underwriting_rules)?
+        .with_modal_factors(modal_factors);
+    info!(
+        "Loaded {} formulas, plan version {}",
+        calculator.formula_names().len(),
+        calculator.plan_version()
+    );
+
+    let mut server = QuoteServer::new(calculator).with_validation_rules(validation_rules);
+    if let Some(catalog) = catalog {
+        let versions = catalog.versions().len();
+        server = server.with_plans(catalog)?;
+        info!("Serving {} plan versions by quote date", versions);
+    }
+
+    let http = QuoteServer::bind(args.port)?;
+    info!("Listening on http://{}", http.server_addr());
+    server.serve(&http);
+    Ok(())
+}
@@ -0,0 +1,510 @@
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use premiumcalc::domain::{parse_conditions, Applicant, Gender};
+use std::path::PathBuf;
+
+/// Command-line interface for the premium calculator
+#[derive(Parser, Debug)]
+#[command(
+    name = "premiumcalc",
+    version,
+    about = "Insurance premium calculator powered by the formcalc formula engine"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Applicant CSV file
+    #[arg(short, long, global = true, default_value = "applicants.csv")]
+    pub input: PathBuf,
+
+    /// Directory of .fc formula scripts [default: built-in formulas]
+    #[arg(long, global = true)]
+    pub formulas: Option<PathBuf>,
+
+    /// Directory of versioned formula sets with a versions.json manifest
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "formulas")]
+    pub plans: Option<PathBuf>,
+
+    /// With --plans, rate with this version instead of the one effective on the quote date
+    #[arg(long, global = true, value_name = "ID", requires = "plans")]
+    pub plan_version: Option<String>,
+
+    /// With --plans, pick the version effective on this date [default: today]
+    #[arg(
+        long,
+        global = true,
+        value_name = "YYYY-MM-DD",
+        requires = "plans",
+        conflicts_with = "plan_version"
+    )]
+    pub quote_date: Option<NaiveDate>,
+
+    /// Occupation risk table, a CSV with occupation,risk_class columns [default: built-in table]
+    #[arg(long, global = true)]
+    pub occupations: Option<PathBuf>,
+
+    /// Base rate table, a CSV with age_min,age_max,gender,smoker,term_min,term_max,rate columns [default: built-in table]
+    #[arg(long, global = true)]
+    pub base_rates: Option<PathBuf>,
+
+    /// Validation rules as JSON, same shape as data/validation_rules.json [default: built-in rules]
+    #[arg(long, global = true)]
+    pub validation_rules: Option<PathBuf>,
+
+    /// Underwriting rules as JSON, same shape as data/underwriting_rules.json [default: built-in rules]
+    #[arg(long, global = true)]
+    pub underwriting_rules: Option<PathBuf>,
+
+    /// Modal factors per payment mode as JSON, same shape as data/modal_factors.json [default: built-in factors]
+    #[arg(long, global = true)]
+    pub modal_factors: Option<PathBuf>,
+
+    /// Number of worker threads [default: one per CPU]
+    #[arg(short = 'j', long, global = true)]
+    pub threads: Option<usize>,
+
+    /// Skip applicant rows that cannot be parsed instead of failing the run
+    #[arg(long, global = true)]
+    pub lenient: bool,
+
+    /// With --lenient, fail when more than this percentage of rows cannot be parsed
+    #[arg(long, global = true, value_name = "PERCENT", requires = "lenient")]
+    pub max_bad_rows: Option<f64>,
+
+    /// Abort on the first applicant whose premium cannot be calculated
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// Record which rule branch each formula took and show it in quotes and the TUI detail view
+    #[arg(long, global = true)]
+    pub explain: bool,
+
+    /// Log verbosity; logs are written to stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogLevel::Info)]
+    pub log_level: LogLevel,
+
+    /// Same as the `benchmark` subcommand, kept for existing scripts
+    #[arg(short = 'b', long, hide = true)]
+    benchmark: bool,
+}
+
+impl Cli {
+    /// Subcommand to run; the TUI when none is given
+    pub fn selected_command(&self) -> Command {
+        match &self.command {
+            _ if self.benchmark => Command::Benchmark,
+            Some(command) => command.clone(),
+            None => Command::Tui,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Rate all applicants and browse the results in the terminal UI (default)
+    Tui,
+    /// Rate all applicants and write one row per applicant
+    Batch(BatchArgs),
+    /// Rate all applicants and report throughput without the UI
+    Benchmark,
+    /// Quote a single applicant given as flags, as JSON on stdin or by id from the input file
+    Quote(QuoteArgs),
+    /// List the formula set versions in --plans and which one is effective on the quote date
+    Versions,
+    /// Rate all applicants with the current and a candidate formula set and report the premium changes
+    Compare(CompareArgs),
+    /// Check the formula dependency graph for missing references, cycles and unused formulas
+    Graph(GraphArgs),
+    /// Check the formulas against golden test cases of inputs and expected outputs
+    Test(TestArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BatchArgs {
+    /// Output file [default: stdout]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Stream the input in chunks of this many rows instead of loading it all,
+    /// keeping memory bounded for very large files
+    #[arg(long, value_name = "ROWS")]
+    pub chunk_size: Option<usize>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct QuoteArgs {
+    /// Quote the applicant with this id from the input file
+    #[arg(long, value_name = "ID", conflicts_with_all = ["stdin", "applicant"])]
+    pub from_input: Option<u32>,
+
+    /// Read the applicant as one JSON object from stdin
+    #[arg(long, conflicts_with = "applicant")]
+    pub stdin: bool,
+
+    #[command(flatten)]
+    pub applicant: ApplicantArgs,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct GraphArgs {
+    /// Write the graph in Graphviz DOT format to this file, `-` for stdout
+    #[arg(long, value_name = "FILE")]
+    pub dot: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct TestArgs {
+    /// Case file, or directory of .json case files [default: the cases directory next to the formula scripts]
+    pub cases: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CompareArgs {
+    /// Candidate formula set: a directory of .fc scripts
+    #[arg(
+        long,
+        value_name = "DIR",
+        required_unless_present = "candidate_version",
+        conflicts_with = "candidate_version"
+    )]
+    pub candidate: Option<PathBuf>,
+
+    /// Candidate formula set: a version id from --plans
+    #[arg(long, value_name = "ID")]
+    pub candidate_version: Option<String>,
+
+    /// Number of biggest movers to list
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+
+    /// Write every applicant's old and new premium as CSV to this file
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Browse old vs new premiums in the terminal UI after the report
+    #[arg(long)]
+    pub tui: bool,
+}
+
+/// Applicant fields for a quote given on the command line
+#[derive(Args, Debug, Clone)]
+#[group(id = "applicant", multiple = true)]
+pub struct ApplicantArgs {
+    /// Applicant id shown on the quote
+    #[arg(long, default_value_t = 0)]
+    pub id: u32,
+
+    /// Applicant name shown on the quote
+    #[arg(long, default_value = "")]
+    pub name: String,
+
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub age: Option<u32>,
+
+    /// M or F; `male` and `female` are accepted too
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub gender: Option<Gender>,
+
+    #[arg(long)]
+    pub smoker: bool,
+
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub occupation: Option<String>,
+
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub annual_income: Option<f64>,
+
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub bmi: Option<f64>,
+
+    /// Systolic blood pressure
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub blood_pressure_sys: Option<u32>,
+
+    /// Diastolic blood pressure
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub blood_pressure_dia: Option<u32>,
+
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub cholesterol: Option<u32>,
+
+    /// Existing conditions, separated by ';': diabetes, hypertension, asthma, arthritis or other
+    #[arg(long, default_value = "none")]
+    pub existing_conditions: String,
+
+    #[arg(long, default_value_t = 0)]
+    pub family_history_score: u32,
+
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub coverage_amount: Option<f64>,
+
+    #[arg(long, required_unless_present_any = ["from_input", "stdin"])]
+    pub coverage_years: Option<u32>,
+}
+
+impl ApplicantArgs {
+    /// Build the applicant, naming the first required flag that is missing
+    pub fn to_applicant(&self) -> Result<Applicant, String> {
+        fn required<T: Clone>(value: &Option<T>, flag: &str) -> Result<T, String> {
+            value
+                .clone()
+                .ok_or_else(|| format!("missing required argument --{}", flag))
+        }
+
+        Ok(Applicant {
+            id: self.id,
+            name: self.name.clone(),
+            age: required(&self.age, "age")?,
+            gender: required(&self.gender, "gender")?,
+            smoker: self.smoker.into(),
+            occupation: required(&self.occupation, "occupation")?,
+            annual_income: required(&self.annual_income, "annual-income")?,
+            bmi: required(&self.bmi, "bmi")?,
+            blood_pressure_sys: required(&self.blood_pressure_sys, "blood-pressure-sys")?,
+            blood_pressure_dia: required(&self.blood_pressure_dia, "blood-pressure-dia")?,
+            cholesterol: required(&self.cholesterol, "cholesterol")?,
+            existing_conditions: parse_conditions(self.existing_conditions.split(';'))?,
+            family_history_score: self.family_history_score,
+            coverage_amount: required(&self.coverage_amount, "coverage-amount")?,
+            coverage_years: required(&self.coverage_years, "coverage-years")?,
+        })
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable table
+    Table,
+    /// Comma-separated values with a header row
+    Csv,
+    /// A single JSON array of objects
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_defaults_to_tui() {
+        let cli = Cli::parse_from(["premiumcalc"]);
+        assert!(matches!(cli.selected_command(), Command::Tui));
+        assert_eq!(cli.input, PathBuf::from("applicants.csv"));
+    }
+
+    #[test]
+    fn test_max_bad_rows_requires_lenient() {
+        assert!(Cli::try_parse_from(["premiumcalc", "--max-bad-rows", "1"]).is_err());
+
+        let cli = Cli::parse_from(["premiumcalc", "--lenient", "--max-bad-rows", "1"]);
+        assert_eq!(cli.max_bad_rows, Some(1.0));
+    }
+
+    #[test]
+    fn test_legacy_benchmark_flag() {
+        let cli = Cli::parse_from(["premiumcalc", "-b"]);
+        assert!(matches!(cli.selected_command(), Command::Benchmark));
+    }
+
+    #[test]
+    fn test_global_options_after_subcommand() {
+        let cli = Cli::parse_from([
+            "premiumcalc",
+            "batch",
+            "--input",
+            "big.csv",
+            "--threads",
+            "4",
+            "--output",
+            "out.txt",
+            "--format",
+            "jsonl",
+        ]);
+        assert_eq!(cli.input, PathBuf::from("big.csv"));
+        assert_eq!(cli.threads, Some(4));
+        match cli.selected_command() {
+            Command::Batch(args) => {
+                assert_eq!(args.output, Some(PathBuf::from("out.txt")));
+                assert_eq!(args.format, OutputFormat::Jsonl);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+
+    fn quote_args(args: &[&str]) -> Result<QuoteArgs, clap::Error> {
+        let cli = Cli::try_parse_from(["premiumcalc", "quote"].iter().chain(args))?;
+        match cli.selected_command() {
+            Command::Quote(args) => Ok(args),
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+
+    const QUOTE_FLAGS: [&str; 20] = [
+        "--age",
+        "45",
+        "--gender",
+        "F",
+        "--occupation",
+        "Nurse",
+        "--annual-income",
+        "65000",
+        "--bmi",
+        "24.5",
+        "--blood-pressure-sys",
+        "125",
+        "--blood-pressure-dia",
+        "82",
+        "--cholesterol",
+        "200",
+        "--coverage-amount",
+        "250000",
+        "--coverage-years",
+        "20",
+    ];
+
+    #[test]
+    fn test_quote_from_flags() {
+        let mut flags = QUOTE_FLAGS.to_vec();
+        flags.push("--smoker");
+        let applicant = quote_args(&flags)
+            .unwrap()
+            .applicant
+            .to_applicant()
+            .unwrap();
+
+        assert_eq!(applicant.age, 45);
+        assert_eq!(applicant.occupation, "Nurse");
+        assert!(applicant.smoker.is_smoker());
+        assert!(applicant.existing_conditions.is_empty());
+        assert_eq!(applicant.coverage_years, 20);
+    }
+
+    #[test]
+    fn test_quote_rejects_unknown_values() {
+        let mut flags = QUOTE_FLAGS.to_vec();
+        flags[3] = "X";
+        let err = quote_args(&flags).unwrap_err().to_string();
+        assert!(err.contains("unknown gender 'X'"), "{}", err);
+
+        let mut flags = QUOTE_FLAGS.to_vec();
+        flags.extend(["--existing-conditions", "diabetis"]);
+        let err = quote_args(&flags)
+            .unwrap()
+            .applicant
+            .to_applicant()
+            .unwrap_err();
+        assert!(err.contains("unknown condition 'diabetis'"), "{}", err);
+    }
+
+    #[test]
+    fn test_quote_requires_applicant_fields() {
+        assert!(quote_args(&["--age", "45"]).is_err());
+    }
+
+    #[test]
+    fn test_quote_from_stdin_needs_no_fields() {
+        let args = quote_args(&["--stdin"]).unwrap();
+        assert!(args.stdin);
+        assert!(args.applicant.to_applicant().is_err());
+    }
+
+    #[test]
+    fn test_quote_stdin_conflicts_with_fields() {
+        assert!(quote_args(&["--stdin", "--age", "45"]).is_err());
+    }
+
+    #[test]
+    fn test_plan_options() {
+        let cli = Cli::parse_from([
+            "premiumcalc",
+            "versions",
+            "--plans",
+            "plans",
+            "--quote-date",
+            "2025-03-01",
+        ]);
+        assert!(matches!(cli.selected_command(), Command::Versions));
+        assert_eq!(cli.quote_date, "2025-03-01".parse().ok());
+
+        assert!(Cli::try_parse_from(["premiumcalc", "--quote-date", "2025-03-01"]).is_err());
+        assert!(
+            Cli::try_parse_from(["premiumcalc", "--plans", "p", "--quote-date", "03/01/2025"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from(["premiumcalc", "--plans", "p", "--formulas", "f"]).is_err());
+    }
+
+    #[test]
+    fn test_compare_needs_a_candidate() {
+        assert!(Cli::try_parse_from(["premiumcalc", "compare"]).is_err());
+        assert!(Cli::try_parse_from([
+            "premiumcalc",
+            "compare",
+            "--candidate",
+            "formulas",
+            "--candidate-version",
+            "2025"
+        ])
+        .is_err());
+
+        let cli = Cli::parse_from([
+            "premiumcalc",
+            "--plans",
+            "plans",
+            "compare",
+            "--candidate-version",
+            "2025",
+            "--top",
+            "5",
+        ]);
+        match cli.selected_command() {
+            Command::Compare(args) => {
+                assert_eq!(args.candidate_version.as_deref(), Some("2025"));
+                assert_eq!(args.top, 5);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_quote_from_input() {
+        let args = quote_args(&["--from-input", "7"]).unwrap();
+        assert_eq!(args.from_input, Some(7));
+    }
+}
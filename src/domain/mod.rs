@@ -1,21 +1,34 @@
-use serde::Deserialize;
+use chrono::NaiveDate;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
 /// Domain model representing an insurance applicant
-#[derive(Debug, Clone, Deserialize)]
+/// Identity, smoker status, conditions and family history default when a quote omits them
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Applicant {
-    #[allow(dead_code)]
+    #[serde(default)]
     pub id: u32,
+    #[serde(default)]
     pub name: String,
     pub age: u32,
-    pub gender: String,
-    pub smoker: bool,
+    pub gender: Gender,
+    #[serde(default)]
+    pub smoker: SmokerStatus,
     pub occupation: String,
     pub annual_income: f64,
     pub bmi: f64,
     pub blood_pressure_sys: u32,
     pub blood_pressure_dia: u32,
     pub cholesterol: u32,
-    pub existing_conditions: String,
+    /// `diabetes;hypertension` in CSV, a string or an array in JSON
+    #[serde(default, deserialize_with = "deserialize_conditions")]
+    pub existing_conditions: Vec<Condition>,
+    #[serde(default)]
     pub family_history_score: u32,
     pub coverage_amount: f64,
     pub coverage_years: u32,
@@ -23,8 +36,260 @@ pub struct Applicant {
 
 impl Applicant {
     pub fn has_existing_conditions(&self) -> bool {
-        self.existing_conditions != "none"
+        !self.existing_conditions.is_empty()
     }
+
+    pub fn has_condition(&self, condition: Condition) -> bool {
+        self.existing_conditions.contains(&condition)
+    }
+}
+
+/// Applicant gender; accepts `M`, `F`, `male` or `female` in any case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gender {
+    Male,
+    Female,
+}
+
+impl Gender {
+    /// Code seen by the formulas and written to exports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Gender::Male => "M",
+            Gender::Female => "F",
+        }
+    }
+}
+
+impl FromStr for Gender {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "m" | "male" => Ok(Gender::Male),
+            "f" | "female" => Ok(Gender::Female),
+            _ => Err(format!(
+                "unknown gender '{}', expected M or F",
+                value.trim()
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Gender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Gender {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Gender {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Whether the applicant smokes; accepts booleans, `yes`/`no`, `y`/`n` and `1`/`0`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SmokerStatus {
+    #[default]
+    NonSmoker,
+    Smoker,
+}
+
+impl SmokerStatus {
+    pub fn is_smoker(&self) -> bool {
+        *self == SmokerStatus::Smoker
+    }
+}
+
+impl From<bool> for SmokerStatus {
+    fn from(smoker: bool) -> Self {
+        if smoker {
+            SmokerStatus::Smoker
+        } else {
+            SmokerStatus::NonSmoker
+        }
+    }
+}
+
+impl FromStr for SmokerStatus {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "true" | "yes" | "y" | "1" | "smoker" => Ok(SmokerStatus::Smoker),
+            "false" | "no" | "n" | "0" | "non-smoker" | "nonsmoker" => Ok(SmokerStatus::NonSmoker),
+            _ => Err(format!(
+                "unknown smoker status '{}', expected true/false, yes/no or 1/0",
+                value.trim()
+            )),
+        }
+    }
+}
+
+/// Written as a boolean so exports keep their `true`/`false` column
+impl Serialize for SmokerStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(self.is_smoker())
+    }
+}
+
+impl<'de> Deserialize<'de> for SmokerStatus {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SmokerVisitor;
+
+        impl Visitor<'_> for SmokerVisitor {
+            type Value = SmokerStatus;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a boolean, yes/no or 1/0")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+                Ok(value.into())
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                match value {
+                    0 | 1 => Ok((value == 1).into()),
+                    _ => Err(E::invalid_value(de::Unexpected::Unsigned(value), &self)),
+                }
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                match value {
+                    0 | 1 => Ok((value == 1).into()),
+                    _ => Err(E::invalid_value(de::Unexpected::Signed(value), &self)),
+                }
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(SmokerVisitor)
+    }
+}
+
+/// An existing medical condition the rating formulas know about
+/// Conditions without a loading of their own are declared as `other`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Diabetes,
+    Hypertension,
+    Asthma,
+    Arthritis,
+    Other,
+}
+
+impl Condition {
+    /// Conditions with their own loading; each is passed to the formulas as a 0/1 variable
+    pub const RATED: [Condition; 4] = [
+        Condition::Diabetes,
+        Condition::Hypertension,
+        Condition::Asthma,
+        Condition::Arthritis,
+    ];
+
+    /// Name of the condition as seen by the formulas
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Condition::Diabetes => "diabetes",
+            Condition::Hypertension => "hypertension",
+            Condition::Asthma => "asthma",
+            Condition::Arthritis => "arthritis",
+            Condition::Other => "other",
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().replace(['_', '-'], " ").as_str() {
+            "diabetes" | "diabetic" => Ok(Condition::Diabetes),
+            "hypertension" | "high blood pressure" | "htn" => Ok(Condition::Hypertension),
+            "asthma" | "asthmatic" => Ok(Condition::Asthma),
+            "arthritis" => Ok(Condition::Arthritis),
+            "other" => Ok(Condition::Other),
+            _ => Err(format!(
+                "unknown condition '{}', expected diabetes, hypertension, asthma, arthritis or other",
+                value.trim()
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Condition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Parse condition names, ignoring case, duplicates, blanks and `none`
+pub fn parse_conditions<'a>(
+    items: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<Condition>, String> {
+    let mut conditions: Vec<Condition> = Vec::new();
+    for item in items {
+        let item = item.trim();
+        if item.is_empty() || item.eq_ignore_ascii_case("none") {
+            continue;
+        }
+        let condition = item.parse()?;
+        if !conditions.contains(&condition) {
+            conditions.push(condition);
+        }
+    }
+    Ok(conditions)
+}
+
+fn deserialize_conditions<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Condition>, D::Error> {
+    struct ConditionsVisitor;
+
+    impl<'de> Visitor<'de> for ConditionsVisitor {
+        type Value = Vec<Condition>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a ';'-separated list of conditions or an array of conditions")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            parse_conditions(value.split(';')).map_err(E::custom)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(Vec::new())
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut items: Vec<String> = Vec::new();
+            while let Some(item) = seq.next_element::<String>()? {
+                items.push(item);
+            }
+            parse_conditions(items.iter().map(String::as_str)).map_err(de::Error::custom)
+        }
+    }
+
+    deserializer.deserialize_any(ConditionsVisitor)
 }
 
 #[cfg(test)]
@@ -36,15 +301,15 @@ mod tests {
             id: 1,
             name: "John Doe".to_string(),
             age: 30,
-            gender: "M".to_string(),
-            smoker: false,
+            gender: Gender::Male,
+            smoker: SmokerStatus::NonSmoker,
             occupation: "Engineer".to_string(),
             annual_income: 75000.0,
             bmi: 22.5,
             blood_pressure_sys: 120,
             blood_pressure_dia: 80,
             cholesterol: 180,
-            existing_conditions: "none".to_string(),
+            existing_conditions: Vec::new(),
             family_history_score: 2,
             coverage_amount: 500000.0,
             coverage_years: 20,
@@ -60,69 +325,568 @@ mod tests {
     #[test]
     fn test_has_existing_conditions_diabetes() {
         let mut applicant = create_test_applicant();
-        applicant.existing_conditions = "diabetes".to_string();
+        applicant.existing_conditions = vec![Condition::Diabetes];
         assert!(applicant.has_existing_conditions());
+        assert!(applicant.has_condition(Condition::Diabetes));
+        assert!(!applicant.has_condition(Condition::Asthma));
+    }
+
+    #[test]
+    fn test_parse_conditions() {
+        assert_eq!(
+            parse_conditions(" Diabetes;high_blood_pressure;;diabetic ".split(';')),
+            Ok(vec![Condition::Diabetes, Condition::Hypertension])
+        );
+        assert_eq!(parse_conditions("None".split(';')), Ok(Vec::new()));
+
+        let err = parse_conditions("asthma;diabetis".split(';')).unwrap_err();
+        assert!(err.contains("unknown condition 'diabetis'"), "{}", err);
+    }
+
+    #[test]
+    fn test_gender_and_smoker_aliases() {
+        assert_eq!("female".parse(), Ok(Gender::Female));
+        assert_eq!(" m ".parse(), Ok(Gender::Male));
+        assert!("X".parse::<Gender>().unwrap_err().contains("'X'"));
+
+        for smoker in ["true", "Yes", "y", "1"] {
+            assert_eq!(smoker.parse(), Ok(SmokerStatus::Smoker));
+        }
+        for non_smoker in ["false", "No", "n", "0"] {
+            assert_eq!(non_smoker.parse(), Ok(SmokerStatus::NonSmoker));
+        }
+        assert!("sometimes".parse::<SmokerStatus>().is_err());
     }
 
     #[test]
-    fn test_annual_premium() {
+    fn test_conditions_from_csv_and_json() {
+        let csv = "id,name,age,gender,smoker,occupation,annual_income,bmi,blood_pressure_sys,blood_pressure_dia,cholesterol,existing_conditions,family_history_score,coverage_amount,coverage_years\n\
+                   1,Jane,40,female,yes,Nurse,65000,24.5,125,82,200,diabetes;Hypertension,2,250000,20\n\
+                   2,John,40,M,0,Clerk,65000,24.5,125,82,200,none,2,250000,20\n";
+        let applicants: Vec<Applicant> = csv::Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            applicants[0].existing_conditions,
+            vec![Condition::Diabetes, Condition::Hypertension]
+        );
+        assert_eq!(applicants[0].gender, Gender::Female);
+        assert!(applicants[0].smoker.is_smoker());
+        assert!(applicants[1].existing_conditions.is_empty());
+        assert!(!applicants[1].smoker.is_smoker());
+
+        let json = r#"{"age":40,"gender":"F","occupation":"Nurse","annual_income":65000,"bmi":24.5,
+            "blood_pressure_sys":125,"blood_pressure_dia":82,"cholesterol":200,
+            "existing_conditions":["Asthma","none"],"coverage_amount":250000,"coverage_years":20}"#;
+        let applicant: Applicant = serde_json::from_str(json).unwrap();
+        assert_eq!(applicant.existing_conditions, vec![Condition::Asthma]);
+
+        let unknown = json.replace("\"F\"", "\"X\"");
+        let err = serde_json::from_str::<Applicant>(&unknown).unwrap_err();
+        assert!(err.to_string().contains("unknown gender 'X'"), "{}", err);
+    }
+
+    #[test]
+    fn test_serializes_codes() {
+        let mut applicant = create_test_applicant();
+        applicant.smoker = SmokerStatus::Smoker;
+        applicant.existing_conditions = vec![Condition::Other];
+        let json = serde_json::to_value(&applicant).unwrap();
+        assert_eq!(json["gender"], "M");
+        assert_eq!(json["smoker"], true);
+        assert_eq!(json["existing_conditions"][0], "other");
+    }
+
+    fn create_test_outputs() -> FormulaOutputs {
+        vec![
+            ("base_premium".to_string(), 250.0),
+            ("age_factor".to_string(), 1.2),
+            ("final_premium".to_string(), 300.0),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn test_rated_annual_premium() {
         let applicant = create_test_applicant();
-        let result = PremiumResult::new(applicant, 250.0, 1.2, 1.0, 1.0, 1.0, 300.0, 0.5);
-        assert_eq!(result.annual_premium(), 3600.0);
+        let result = PremiumResult::new(applicant, 250.0, 300.0, create_test_outputs(), 0.5);
+        assert_eq!(result.rated_annual_premium(), 3600.0);
+    }
+
+    #[test]
+    fn test_installment_is_what_billing_charges() {
+        let mut result = PremiumResult::new(
+            create_test_applicant(),
+            250.0,
+            300.0,
+            create_test_outputs(),
+            0.5,
+        );
+        assert_eq!(result.installment(PaymentMode::Quarterly), 900.0);
+        assert_eq!(result.billed_annual(PaymentMode::Monthly), 3600.0);
+
+        result.payment_options = vec![ModalPremium {
+            mode: PaymentMode::Monthly,
+            factor: 1.02,
+            installment: 306.0,
+            annual_total: 3672.0,
+        }];
+        assert_eq!(result.installment(PaymentMode::Monthly), 306.0);
+        assert_eq!(result.billed_annual(PaymentMode::Monthly), 3672.0);
     }
 
     #[test]
     fn test_premium_result_creation() {
         let applicant = create_test_applicant();
-        let result = PremiumResult::new(applicant.clone(), 250.0, 1.2, 1.0, 1.0, 1.0, 300.0, 0.5);
+        let result =
+            PremiumResult::new(applicant.clone(), 250.0, 300.0, create_test_outputs(), 0.5);
 
         assert_eq!(result.base_premium, 250.0);
-        assert_eq!(result.age_factor, 1.2);
+        assert_eq!(result.outputs.get("age_factor"), Some(1.2));
         assert_eq!(result.final_premium, 300.0);
         assert_eq!(result.calculation_time_ms, 0.5);
         assert_eq!(result.applicant.id, applicant.id);
     }
+
+    #[test]
+    fn test_formula_outputs_keep_insertion_order() {
+        let mut outputs = create_test_outputs();
+        outputs.insert("age_factor", 1.5);
+        outputs.insert("duration_discount", 0.97);
+
+        let names: Vec<&str> = outputs.iter().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "base_premium",
+                "age_factor",
+                "final_premium",
+                "duration_discount"
+            ]
+        );
+        assert_eq!(outputs.get("age_factor"), Some(1.5));
+        assert_eq!(outputs.get("missing"), None);
+    }
+}
+
+/// Numeric formula outputs keyed by formula name, in execution order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormulaOutputs {
+    entries: Vec<(String, f64)>,
+}
+
+impl FormulaOutputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an output, replacing any previous value for the same formula
+    pub fn insert(&mut self, name: impl Into<String>, value: f64) {
+        let name = name.into();
+        match self
+            .entries
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((name, value)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.entries
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+    }
+}
+
+/// Serialized as an object keyed by formula name, in execution order
+impl Serialize for FormulaOutputs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+        for (name, value) in &self.entries {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl FromIterator<(String, f64)> for FormulaOutputs {
+    fn from_iter<I: IntoIterator<Item = (String, f64)>>(iter: I) -> Self {
+        let mut outputs = Self::new();
+        for (name, value) in iter {
+            outputs.insert(name, value);
+        }
+        outputs
+    }
+}
+
+/// Underwriting outcome, ordered from least to most severe
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// Accepted on standard terms
+    #[default]
+    Standard,
+    /// Accepted with a loading above standard terms
+    Rated,
+    /// Needs a manual review by an underwriter
+    Refer,
+    Decline,
+}
+
+impl Decision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Decision::Standard => "standard",
+            Decision::Rated => "rated",
+            Decision::Refer => "refer",
+            Decision::Decline => "decline",
+        }
+    }
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// Financial limits derived from the applicant's income, and whether the quote breaks them
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Affordability {
+    /// Largest coverage the income supports at the applicant's age
+    pub max_coverage: f64,
+    /// Largest annual premium within the premium-to-income limit
+    pub max_annual_premium: f64,
+    pub coverage_exceeded: bool,
+    pub premium_exceeded: bool,
+}
+
+impl Affordability {
+    pub fn is_affordable(&self) -> bool {
+        !self.coverage_exceeded && !self.premium_exceeded
+    }
+}
+
+/// Underwriting decision with the reasons that triggered it
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Underwriting {
+    pub decision: Decision,
+    pub reasons: Vec<String>,
+    pub affordability: Affordability,
+}
+
+/// A named formula set and the date it takes effect
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlanVersion {
+    pub id: String,
+    pub effective_from: NaiveDate,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// A value given to or expected from a formula in a test case
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum CaseValue {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for CaseValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaseValue::Bool(value) => write!(f, "{}", value),
+            CaseValue::Number(value) => write!(f, "{}", value),
+            CaseValue::Text(value) => write!(f, "'{}'", value),
+        }
+    }
+}
+
+/// Inputs for the formulas and the outputs they must produce, written by the rate author
+/// Inputs name variables, or formulas whose output is taken as given instead of evaluated
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormulaCase {
+    /// File the case was loaded from, filled in by the loader
+    #[serde(skip)]
+    pub file: String,
+    pub name: String,
+    #[serde(default)]
+    pub inputs: BTreeMap<String, CaseValue>,
+    /// Expected output per formula name
+    pub expected: BTreeMap<String, CaseValue>,
+    /// Largest accepted difference for numeric outputs [default: 1e-6]
+    #[serde(default)]
+    pub tolerance: Option<f64>,
+}
+
+/// How often the premium is billed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMode {
+    Monthly,
+    Quarterly,
+    SemiAnnual,
+    Annual,
+}
+
+impl PaymentMode {
+    pub const ALL: [PaymentMode; 4] = [
+        PaymentMode::Monthly,
+        PaymentMode::Quarterly,
+        PaymentMode::SemiAnnual,
+        PaymentMode::Annual,
+    ];
+
+    pub fn installments_per_year(&self) -> u32 {
+        match self {
+            PaymentMode::Monthly => 12,
+            PaymentMode::Quarterly => 4,
+            PaymentMode::SemiAnnual => 2,
+            PaymentMode::Annual => 1,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMode::Monthly => "monthly",
+            PaymentMode::Quarterly => "quarterly",
+            PaymentMode::SemiAnnual => "semi_annual",
+            PaymentMode::Annual => "annual",
+        }
+    }
+
+    /// Name shown to people, e.g. `Semi-Annual`
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaymentMode::Monthly => "Monthly",
+            PaymentMode::Quarterly => "Quarterly",
+            PaymentMode::SemiAnnual => "Semi-Annual",
+            PaymentMode::Annual => "Annual",
+        }
+    }
+}
+
+/// What billing charges in one payment mode
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ModalPremium {
+    pub mode: PaymentMode,
+    /// Multiplier applied to the annual premium for this mode
+    pub factor: f64,
+    /// Amount of each installment, rounded to cents
+    pub installment: f64,
+    /// Total billed over a year, `installment` times the installments per year
+    pub annual_total: f64,
+}
+
+/// How one formula reached its output, recorded in explain mode
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormulaTrace {
+    pub formula: String,
+    /// Variables and formula outputs read on the path the formula took, with their values
+    pub inputs: Vec<(String, String)>,
+    /// Condition of each branch taken, outermost first, e.g. `age < 50`
+    /// Empty for formulas without branches
+    pub branch: Vec<String>,
+    pub output: String,
+}
+
+impl FormulaTrace {
+    /// Conditions that held, such as `age >= 40 and smoker`
+    pub fn branch_label(&self) -> Option<String> {
+        if self.branch.is_empty() {
+            None
+        } else {
+            Some(self.branch.join(", then "))
+        }
+    }
+
+    /// Inputs as `name = value` pairs
+    pub fn inputs_label(&self) -> String {
+        self.inputs
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 /// Value object representing the premium calculation result
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PremiumResult {
     pub applicant: Applicant,
     pub base_premium: f64,
-    pub age_factor: f64,
-    pub health_risk_score: f64,
-    pub lifestyle_multiplier: f64,
-    pub occupation_factor: f64,
     pub final_premium: f64,
+    pub outputs: FormulaOutputs,
     pub calculation_time_ms: f64,
+    pub warnings: Vec<String>,
+    pub underwriting: Underwriting,
+    /// Cost of each payment mode, in `PaymentMode::ALL` order
+    pub payment_options: Vec<ModalPremium>,
+    /// Id of the formula set version that rated this applicant
+    pub plan_version: String,
+    /// How each formula reached its output, in execution order; empty unless explain mode is on
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trace: Vec<FormulaTrace>,
 }
 
 impl PremiumResult {
-    #[allow(clippy::too_many_arguments)]
     pub fn new(
         applicant: Applicant,
         base_premium: f64,
-        age_factor: f64,
-        health_risk_score: f64,
-        lifestyle_multiplier: f64,
-        occupation_factor: f64,
         final_premium: f64,
+        outputs: FormulaOutputs,
         calculation_time_ms: f64,
     ) -> Self {
         Self {
             applicant,
             base_premium,
-            age_factor,
-            health_risk_score,
-            lifestyle_multiplier,
-            occupation_factor,
             final_premium,
+            outputs,
             calculation_time_ms,
+            warnings: Vec::new(),
+            underwriting: Underwriting::default(),
+            payment_options: Vec::new(),
+            plan_version: String::new(),
+            trace: Vec::new(),
         }
     }
 
-    #[allow(dead_code)]
-    pub fn annual_premium(&self) -> f64 {
+    /// Rated premium for a year of cover, before modal factors
+    pub fn rated_annual_premium(&self) -> f64 {
         self.final_premium * 12.0
     }
+
+    /// What billing charges in the given payment mode, if it was calculated
+    pub fn payment(&self, mode: PaymentMode) -> Option<&ModalPremium> {
+        self.payment_options
+            .iter()
+            .find(|option| option.mode == mode)
+    }
+
+    /// Amount of each installment billed in a payment mode
+    /// Without payment options this is the rated premium split evenly, with no modal factor
+    pub fn installment(&self, mode: PaymentMode) -> f64 {
+        self.payment(mode)
+            .map(|option| option.installment)
+            .unwrap_or_else(|| self.rated_annual_premium() / mode.installments_per_year() as f64)
+    }
+
+    /// Total billed over a year in a payment mode, with the same fallback as `installment`
+    pub fn billed_annual(&self, mode: PaymentMode) -> f64 {
+        self.payment(mode)
+            .map(|option| option.annual_total)
+            .unwrap_or_else(|| self.rated_annual_premium())
+    }
 }
+
+/// Occupational risk class used to rate an applicant's job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OccupationClass {
+    Office,
+    LightManual,
+    HeavyManual,
+    Hazardous,
+}
+
+impl OccupationClass {
+    /// Name of the class as seen by the formulas
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OccupationClass::Office => "office",
+            OccupationClass::LightManual => "light_manual",
+            OccupationClass::HeavyManual => "heavy_manual",
+            OccupationClass::Hazardous => "hazardous",
+        }
+    }
+}
+
+/// Value object describing an applicant whose premium could not be calculated
+#[derive(Debug, Clone, Serialize)]
+pub struct CalculationFailure {
+    pub applicant_id: u32,
+    pub formula: String,
+    pub message: String,
+}
+
+impl fmt::Display for CalculationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "applicant {}: formula '{}' failed: {}",
+            self.applicant_id, self.formula, self.message
+        )
+    }
+}
+
+impl Error for CalculationFailure {}
+
+/// A validation rule broken by an applicant record
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    pub rule: String,
+    pub message: String,
+}
+
+/// Value object describing an applicant record rejected before calculation
+#[derive(Debug, Clone, Serialize)]
+pub struct Rejection {
+    pub applicant_id: u32,
+    pub violations: Vec<Violation>,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self
+            .violations
+            .iter()
+            .map(|violation| violation.message.as_str())
+            .collect();
+        write!(
+            f,
+            "applicant {} rejected: {}",
+            self.applicant_id,
+            messages.join("; ")
+        )
+    }
+}
+
+impl Error for Rejection {}
+
+/// Value object describing an input row that could not be parsed into an applicant
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowError {
+    pub line: u64,
+    pub column: Option<String>,
+    pub raw: String,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.column {
+            Some(column) => write!(
+                f,
+                "line {}, column '{}': {} [{}]",
+                self.line, column, self.message, self.raw
+            ),
+            None => write!(f, "line {}: {} [{}]", self.line, self.message, self.raw),
+        }
+    }
+}
+
+impl Error for RowError {}
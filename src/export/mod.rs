@@ -0,0 +1,483 @@
+use crate::domain::{PaymentMode, PremiumResult};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::error::Error;
+use std::io::Write;
+
+/// Streams calculated premiums to an output, one applicant at a time
+/// Rows are written as they are passed in, so exports never hold a second copy of the results
+pub trait ResultWriter {
+    /// Write one applicant's premium
+    fn write_result(&mut self, result: &PremiumResult) -> Result<(), Box<dyn Error>>;
+
+    /// Write any trailer the format needs and flush the output
+    fn finish(&mut self) -> Result<(), Box<dyn Error>>;
+}
+
+/// Write every result and finish the output, returning the number of rows written
+pub fn write_all<'a>(
+    writer: &mut dyn ResultWriter,
+    results: impl IntoIterator<Item = &'a PremiumResult>,
+) -> Result<usize, Box<dyn Error>> {
+    let mut rows = 0;
+    for result in results {
+        writer.write_result(result)?;
+        rows += 1;
+    }
+    writer.finish()?;
+    Ok(rows)
+}
+
+/// Value of a single exported column
+#[derive(Debug, Clone, PartialEq)]
+enum Cell<'a> {
+    Id(u32),
+    Text(&'a str),
+    Number(Option<f64>),
+    /// Amount of money, written to cents in CSV
+    Money(Option<f64>),
+    List(&'a [String]),
+}
+
+impl Cell<'_> {
+    fn to_csv_field(&self) -> String {
+        match self {
+            Cell::Id(id) => id.to_string(),
+            Cell::Text(text) => text.to_string(),
+            Cell::Number(Some(value)) => value.to_string(),
+            Cell::Money(Some(value)) => format!("{:.2}", value),
+            Cell::Number(None) | Cell::Money(None) => String::new(),
+            Cell::List(items) => items.join("; "),
+        }
+    }
+}
+
+impl Serialize for Cell<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Cell::Id(id) => serializer.serialize_u32(*id),
+            Cell::Text(text) => serializer.serialize_str(text),
+            Cell::Number(Some(value)) | Cell::Money(Some(value)) => {
+                serializer.serialize_f64(*value)
+            }
+            Cell::Number(None) | Cell::Money(None) => serializer.serialize_none(),
+            Cell::List(items) => items.serialize(serializer),
+        }
+    }
+}
+
+/// Column layout shared by every export format:
+/// id, name, one column per formula output, the rated annual premium, the installment of each
+/// payment mode, the income-based limits, the underwriting decision and its reasons, then the
+/// version of the formula set that rated the applicant
+#[derive(Debug, Clone)]
+struct ExportColumns {
+    outputs: Vec<String>,
+    payments: Vec<String>,
+}
+
+impl ExportColumns {
+    fn new(outputs: Vec<String>) -> Self {
+        let payments = PaymentMode::ALL
+            .iter()
+            .map(|mode| format!("{}_payment", mode.as_str()))
+            .collect();
+        Self { outputs, payments }
+    }
+
+    fn header(&self) -> Vec<&str> {
+        let mut header = vec!["id", "name"];
+        header.extend(self.outputs.iter().map(String::as_str));
+        header.push("rated_annual_premium");
+        header.extend(self.payments.iter().map(String::as_str));
+        header.extend([
+            "max_coverage",
+            "max_annual_premium",
+            "decision",
+            "reasons",
+            "plan_version",
+        ]);
+        header
+    }
+
+    fn cells<'a>(&'a self, result: &'a PremiumResult) -> Vec<(&'a str, Cell<'a>)> {
+        let mut cells = vec![
+            ("id", Cell::Id(result.applicant.id)),
+            ("name", Cell::Text(&result.applicant.name)),
+        ];
+        cells.extend(
+            self.outputs
+                .iter()
+                .map(|name| (name.as_str(), Cell::Number(result.outputs.get(name)))),
+        );
+        cells.push((
+            "rated_annual_premium",
+            Cell::Money(Some(result.rated_annual_premium())),
+        ));
+        cells.extend(
+            self.payments
+                .iter()
+                .zip(PaymentMode::ALL)
+                .map(|(name, mode)| {
+                    (
+                        name.as_str(),
+                        Cell::Money(result.payment(mode).map(|option| option.installment)),
+                    )
+                }),
+        );
+        cells.extend([
+            (
+                "max_coverage",
+                Cell::Money(Some(result.underwriting.affordability.max_coverage)),
+            ),
+            (
+                "max_annual_premium",
+                Cell::Money(Some(result.underwriting.affordability.max_annual_premium)),
+            ),
+            (
+                "decision",
+                Cell::Text(result.underwriting.decision.as_str()),
+            ),
+            ("reasons", Cell::List(&result.underwriting.reasons)),
+            ("plan_version", Cell::Text(&result.plan_version)),
+        ]);
+        cells
+    }
+}
+
+/// One result serialized as a JSON object with keys in column order
+struct JsonRow<'a> {
+    columns: &'a ExportColumns,
+    result: &'a PremiumResult,
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let cells = self.columns.cells(self.result);
+        let mut map = serializer.serialize_map(Some(cells.len()))?;
+        for (name, cell) in &cells {
+            map.serialize_entry(name, cell)?;
+        }
+        map.end()
+    }
+}
+
+/// Comma-separated values with a header row
+pub struct CsvResultWriter<W: Write> {
+    writer: csv::Writer<W>,
+    columns: ExportColumns,
+    header_written: bool,
+}
+
+impl<W: Write> CsvResultWriter<W> {
+    pub fn new(writer: W, outputs: Vec<String>) -> Self {
+        Self {
+            writer: csv::Writer::from_writer(writer),
+            columns: ExportColumns::new(outputs),
+            header_written: false,
+        }
+    }
+
+    fn write_header(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.header_written {
+            self.writer.write_record(self.columns.header())?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ResultWriter for CsvResultWriter<W> {
+    fn write_result(&mut self, result: &PremiumResult) -> Result<(), Box<dyn Error>> {
+        self.write_header()?;
+        let record: Vec<String> = self
+            .columns
+            .cells(result)
+            .iter()
+            .map(|(_, cell)| cell.to_csv_field())
+            .collect();
+        self.writer.write_record(&record)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        // An empty export still gets a header so downstream tools see the columns
+        self.write_header()?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A single JSON array of objects, written element by element
+pub struct JsonResultWriter<W: Write> {
+    writer: W,
+    columns: ExportColumns,
+    rows_written: usize,
+}
+
+impl<W: Write> JsonResultWriter<W> {
+    pub fn new(writer: W, outputs: Vec<String>) -> Self {
+        Self {
+            writer,
+            columns: ExportColumns::new(outputs),
+            rows_written: 0,
+        }
+    }
+}
+
+impl<W: Write> ResultWriter for JsonResultWriter<W> {
+    fn write_result(&mut self, result: &PremiumResult) -> Result<(), Box<dyn Error>> {
+        let separator = if self.rows_written == 0 { "[\n" } else { ",\n" };
+        self.writer.write_all(separator.as_bytes())?;
+        let row = JsonRow {
+            columns: &self.columns,
+            result,
+        };
+        serde_json::to_writer(&mut self.writer, &row)?;
+        self.rows_written += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        let trailer = if self.rows_written == 0 {
+            "[]\n"
+        } else {
+            "\n]\n"
+        };
+        self.writer.write_all(trailer.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// One JSON object per line
+pub struct JsonLinesResultWriter<W: Write> {
+    writer: W,
+    columns: ExportColumns,
+}
+
+impl<W: Write> JsonLinesResultWriter<W> {
+    pub fn new(writer: W, outputs: Vec<String>) -> Self {
+        Self {
+            writer,
+            columns: ExportColumns::new(outputs),
+        }
+    }
+}
+
+impl<W: Write> ResultWriter for JsonLinesResultWriter<W> {
+    fn write_result(&mut self, result: &PremiumResult) -> Result<(), Box<dyn Error>> {
+        let row = JsonRow {
+            columns: &self.columns,
+            result,
+        };
+        serde_json::to_writer(&mut self.writer, &row)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Human-readable table with what billing charges monthly and annually, and the decision,
+/// per applicant
+pub struct TableResultWriter<W: Write> {
+    writer: W,
+    header_written: bool,
+}
+
+impl<W: Write> TableResultWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            header_written: false,
+        }
+    }
+
+    fn write_header(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.header_written {
+            writeln!(
+                self.writer,
+                "{:>8}  {:<24}  {:>12}  {:>12}  {:<8}",
+                "id", "name", "monthly", "annual", "decision"
+            )?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ResultWriter for TableResultWriter<W> {
+    fn write_result(&mut self, result: &PremiumResult) -> Result<(), Box<dyn Error>> {
+        self.write_header()?;
+        writeln!(
+            self.writer,
+            "{:>8}  {:<24}  {:>12.2}  {:>12.2}  {:<8}",
+            result.applicant.id,
+            result.applicant.name,
+            result.installment(PaymentMode::Monthly),
+            result.installment(PaymentMode::Annual),
+            result.underwriting.decision
+        )?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.write_header()?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Applicant, Decision, FormulaOutputs};
+    use crate::services::ModalFactors;
+
+    fn result(id: u32, name: &str, final_premium: f64) -> PremiumResult {
+        let applicant = Applicant {
+            name: name.to_string(),
+            ..crate::test_support::applicant(id)
+        };
+        let outputs: FormulaOutputs = vec![
+            ("base_premium".to_string(), 100.0),
+            ("age_factor".to_string(), 1.2),
+            ("final_premium".to_string(), final_premium),
+        ]
+        .into_iter()
+        .collect();
+        PremiumResult::new(applicant, 100.0, final_premium, outputs, 0.1)
+    }
+
+    fn outputs() -> Vec<String> {
+        vec![
+            "base_premium".to_string(),
+            "age_factor".to_string(),
+            "final_premium".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let mut buffer = Vec::new();
+        let mut writer = CsvResultWriter::new(&mut buffer, outputs());
+        let mut rated = result(2, "John", 150.0);
+        rated.underwriting.affordability.max_coverage = 2000000.0;
+        rated.underwriting.affordability.max_annual_premium = 20000.0;
+        rated.payment_options =
+            ModalFactors::default().payment_options(rated.rated_annual_premium());
+        rated.underwriting.decision = Decision::Rated;
+        rated.plan_version = "2025-q1".to_string();
+        rated.underwriting.reasons = vec![
+            "health_risk_score 2.4 is above 1.5".to_string(),
+            "lifestyle_multiplier 1.8 is above 1".to_string(),
+        ];
+        let rows = write_all(&mut writer, &[result(1, "Jane, Doe", 120.0), rated]).unwrap();
+        drop(writer);
+
+        assert_eq!(rows, 2);
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "id,name,base_premium,age_factor,final_premium,rated_annual_premium,\
+             monthly_payment,quarterly_payment,semi_annual_payment,annual_payment,\
+             max_coverage,max_annual_premium,decision,reasons,plan_version\n\
+             1,\"Jane, Doe\",100,1.2,120,1440.00,,,,,0.00,0.00,standard,,\n\
+             2,John,100,1.2,150,1800.00,153.00,454.50,900.00,1746.00,2000000.00,20000.00,rated,health_risk_score 2.4 is above 1.5; lifestyle_multiplier 1.8 is above 1,2025-q1\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_export_without_results_writes_header() {
+        let mut buffer = Vec::new();
+        let mut writer = CsvResultWriter::new(&mut buffer, outputs());
+        write_all(&mut writer, &[]).unwrap();
+        drop(writer);
+
+        assert!(String::from_utf8(buffer)
+            .unwrap()
+            .starts_with("id,name,base_premium"));
+    }
+
+    #[test]
+    fn test_json_export_is_an_array_in_column_order() {
+        let mut buffer = Vec::new();
+        let mut writer = JsonResultWriter::new(&mut buffer, outputs());
+        write_all(
+            &mut writer,
+            &[result(1, "Jane", 120.0), result(2, "John", 150.0)],
+        )
+        .unwrap();
+        drop(writer);
+
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.starts_with("[\n{\"id\":1,\"name\":\"Jane\",\"base_premium\":100.0"));
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&text).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["rated_annual_premium"], 1800.0);
+        assert_eq!(rows[1]["decision"], "standard");
+        assert!(rows[1]["reasons"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_json_export_without_results_is_empty_array() {
+        let mut buffer = Vec::new();
+        let mut writer = JsonResultWriter::new(&mut buffer, outputs());
+        write_all(&mut writer, &[]).unwrap();
+        drop(writer);
+
+        let rows: Vec<serde_json::Value> = serde_json::from_slice(&buffer).unwrap();
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn test_jsonl_export_one_object_per_line() {
+        let mut buffer = Vec::new();
+        let mut writer = JsonLinesResultWriter::new(&mut buffer, outputs());
+        write_all(
+            &mut writer,
+            &[result(1, "Jane", 120.0), result(2, "John", 150.0)],
+        )
+        .unwrap();
+        drop(writer);
+
+        let text = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let row: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(row["id"], 2);
+        assert_eq!(row["age_factor"], 1.2);
+    }
+
+    #[test]
+    fn test_table_shows_billed_installments() {
+        let mut buffer = Vec::new();
+        let mut writer = TableResultWriter::new(&mut buffer);
+        let mut rated = result(1, "Jane", 150.0);
+        rated.payment_options =
+            ModalFactors::default().payment_options(rated.rated_annual_premium());
+        write_all(&mut writer, &[rated]).unwrap();
+
+        let text = String::from_utf8(buffer).unwrap();
+        let row = text.lines().nth(1).unwrap();
+        assert_eq!(
+            row.split_whitespace().collect::<Vec<_>>(),
+            vec!["1", "Jane", "153.00", "1746.00", "standard"]
+        );
+    }
+
+    #[test]
+    fn test_missing_output_is_null() {
+        let mut buffer = Vec::new();
+        let mut outputs = outputs();
+        outputs.push("bmi_risk".to_string());
+        let mut writer = JsonLinesResultWriter::new(&mut buffer, outputs);
+        write_all(&mut writer, &[result(1, "Jane", 120.0)]).unwrap();
+        drop(writer);
+
+        let row: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert!(row["bmi_risk"].is_null());
+    }
+}
@@ -0,0 +1,19 @@
+// Domain-Driven Design and SOLID Principles Applied
+// - Domain: Models representing core business entities
+// - Services: Business logic for premium calculation
+// - Repository: Data access abstraction
+// - Application: Use case orchestration
+// - Export: Streaming result writers
+// - UI: Presentation layer
+// - Server: Local HTTP quoting service (`server` feature)
+
+pub mod application;
+pub mod domain;
+pub mod export;
+pub mod repository;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod services;
+#[cfg(test)]
+mod test_support;
+pub mod ui;
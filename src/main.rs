@@ -1,84 +1,453 @@
-// Domain-Driven Design and SOLID Principles Applied
-// - Domain: Models representing core business entities
-// - Services: Business logic for premium calculation
-// - Repository: Data access abstraction
-// - Application: Use case orchestration
-// - UI: Presentation layer
-
-mod application;
-mod domain;
-mod repository;
-mod services;
-mod ui;
-
-use application::PremiumCalculationApp;
+mod cli;
+
+use chrono::NaiveDate;
+use clap::Parser;
+use cli::{BatchArgs, Cli, Command, CompareArgs, GraphArgs, OutputFormat, QuoteArgs, TestArgs};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use log::{debug, info, warn};
+use premiumcalc::application::{CalculationBatch, PremiumCalculationApp};
+use premiumcalc::domain::{
+    Applicant, CalculationFailure, PlanVersion, PremiumResult, Rejection, RowError,
+};
+use premiumcalc::export::{
+    self, CsvResultWriter, JsonLinesResultWriter, JsonResultWriter, ResultWriter, TableResultWriter,
+};
+use premiumcalc::repository::{
+    load_formula_cases, BaseRateTable, CsvApplicantRepository, FileFormulaRepository,
+    FormulaRepository, FormulaWatcher, InMemoryFormulaRepository, OccupationRiskTable, PlanCatalog,
+    CASES_DIRECTORY,
+};
+use premiumcalc::services::{
+    counts_by_rule, DependencyGraph, ModalFactors, PremiumComparison, UnderwritingRules,
+    ValidationRules, RESULT_FORMULAS,
+};
+use premiumcalc::ui::{self, AppState, Banner};
 use ratatui::{backend::CrosstermBackend, Terminal};
-use repository::{CsvApplicantRepository, InMemoryFormulaRepository};
-use std::{error::Error, io, time::Duration};
-use ui::AppState;
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    // Check for benchmark mode
-    let args: Vec<String> = std::env::args().collect();
-    let benchmark_mode =
-        args.contains(&"--benchmark".to_string()) || args.contains(&"-b".to_string());
-
-    // Initialize application with CSV repository and formula repository
-    let applicant_repository = Box::new(CsvApplicantRepository::new("applicants.csv".to_string()));
-    let formula_repository = Box::new(InMemoryFormulaRepository::new());
-    let app = PremiumCalculationApp::new(applicant_repository, formula_repository);
-
-    // Load applicants from repository
-    let applicants = app.load_applicants()?;
-    let applicant_count = applicants.len();
+    let cli = Cli::parse();
+
+    env_logger::Builder::new()
+        .filter_level(cli.log_level.into())
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
+
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+
+    match cli.selected_command() {
+        // These commands only read the plan manifest or the formula scripts, so no formulas
+        // are compiled and a broken formula set can still be inspected
+        Command::Versions => run_versions(&cli),
+        Command::Graph(args) => run_graph(&cli, &args),
+        Command::Tui => run_tui(&mut build_app(&cli)?, &cli),
+        Command::Batch(args) => run_batch(&build_app(&cli)?, &cli, &args),
+        Command::Benchmark => run_benchmark(&build_app(&cli)?, &cli),
+        Command::Quote(args) => run_quote(&build_app(&cli)?, &args),
+        Command::Compare(args) => run_compare(&build_app(&cli)?, &cli, &args),
+        Command::Test(args) => run_test(&build_app(&cli)?, &args),
+    }
+}
+
+/// Date used to pick the plan version, today unless --quote-date is given
+fn quote_date(cli: &Cli) -> NaiveDate {
+    cli.quote_date
+        .unwrap_or_else(|| chrono::Local::now().date_naive())
+}
+
+/// Formula set version requested with --plan-version, or the one effective on the quote date
+fn select_plan_version<'a>(
+    catalog: &'a PlanCatalog,
+    cli: &Cli,
+) -> Result<&'a PlanVersion, Box<dyn Error>> {
+    match &cli.plan_version {
+        Some(id) => catalog.get(id),
+        None => catalog.effective_on(quote_date(cli)),
+    }
+}
+
+/// Formulas come from a plan version or script files when a directory is given,
+/// otherwise the built-in set
+fn formula_repository(cli: &Cli) -> Result<Box<dyn FormulaRepository>, Box<dyn Error>> {
+    Ok(match (&cli.plans, &cli.formulas) {
+        (Some(dir), _) => {
+            let catalog = PlanCatalog::open(dir)?;
+            let version = select_plan_version(&catalog, cli)?;
+            info!(
+                "Using plan version {} (effective from {})",
+                version.id, version.effective_from
+            );
+            Box::new(catalog.repository(version))
+        }
+        (None, Some(dir)) => Box::new(FileFormulaRepository::new(dir)),
+        (None, None) => Box::new(InMemoryFormulaRepository::new()),
+    })
+}
+
+/// Initialize application with CSV repository and formula repository
+fn build_app(cli: &Cli) -> Result<PremiumCalculationApp, Box<dyn Error>> {
+    let applicant_repository = Box::new(CsvApplicantRepository::new(
+        cli.input.to_string_lossy().into_owned(),
+    ));
+
+    let formula_repository = formula_repository(cli)?;
+
+    let occupation_table = match &cli.occupations {
+        Some(path) => OccupationRiskTable::from_csv(&path.to_string_lossy())?,
+        None => OccupationRiskTable::default(),
+    };
+    let base_rate_table = match &cli.base_rates {
+        Some(path) => BaseRateTable::from_csv(&path.to_string_lossy())?,
+        None => BaseRateTable::default(),
+    };
+
+    let underwriting_rules = match &cli.underwriting_rules {
+        Some(path) => UnderwritingRules::from_json(&path.to_string_lossy())?,
+        None => UnderwritingRules::default(),
+    };
+
+    let modal_factors = match &cli.modal_factors {
+        Some(path) => ModalFactors::from_json(&path.to_string_lossy())?,
+        None => ModalFactors::default(),
+    };
+
+    let validation_rules = match &cli.validation_rules {
+        Some(path) => ValidationRules::from_json(&path.to_string_lossy())?,
+        None => ValidationRules::default(),
+    };
+
+    let app = PremiumCalculationApp::new(applicant_repository, formula_repository)?
+        .with_strict_mode(cli.strict)
+        .with_occupation_table(occupation_table)
+        .with_base_rate_table(base_rate_table)
+        .with_underwriting_rules(underwriting_rules)?
+        .with_modal_factors(modal_factors)
+        .with_validation_rules(validation_rules)
+        .with_explain(cli.explain);
+    info!("Loaded {} formulas", app.formula_names().len());
+    for name in app.unused_formulas() {
+        debug!("Formula '{}' is not used by any result formula", name);
+    }
+    if !app.unused_variables().is_empty() {
+        debug!(
+            "Variables not read by any formula: {}",
+            app.unused_variables().join(", ")
+        );
+    }
+
+    Ok(app)
+}
+
+/// Report problems in the formula dependency graph and optionally export it as DOT
+fn run_graph(cli: &Cli, args: &GraphArgs) -> Result<(), Box<dyn Error>> {
+    let repository = formula_repository(cli)?;
+    let formulas = repository.load_all()?;
+    let graph = DependencyGraph::new(&formulas);
+    let report = graph.analyze(&RESULT_FORMULAS);
+
+    match args.dot.as_deref() {
+        Some(path) if path == Path::new("-") => print!("{}", graph.to_dot()),
+        Some(path) => {
+            std::fs::write(path, graph.to_dot())?;
+            info!("Wrote dependency graph to {}", path.display());
+        }
+        None => {}
+    }
+
+    for message in report.messages() {
+        warn!("{}", message);
+    }
+    if report.has_errors() {
+        return Err(format!(
+            "formula set {} has {} missing references and {} cycles",
+            repository.version_id(),
+            report.missing.len(),
+            report.cycles.len()
+        )
+        .into());
+    }
+    info!(
+        "{} formulas checked: no missing references or cycles, {} unused",
+        formulas.len(),
+        report.unused.len()
+    );
+    Ok(())
+}
+
+/// Check the loaded formulas against golden test cases and list every check
+fn run_test(app: &PremiumCalculationApp, args: &TestArgs) -> Result<(), Box<dyn Error>> {
+    let path = match (&args.cases, app.formula_directory()) {
+        (Some(path), _) => path.clone(),
+        (None, Some(dir)) => dir.join(CASES_DIRECTORY),
+        (None, None) => {
+            return Err(
+                "the built-in formulas have no case directory; give one, e.g. formulas/cases"
+                    .into(),
+            )
+        }
+    };
+    let cases = load_formula_cases(&path)?;
+    let report = app.run_formula_cases(&cases);
+
+    let mut out = io::stdout().lock();
+    for check in &report.checks {
+        writeln!(
+            out,
+            "{} {}",
+            if check.passed() { "ok  " } else { "FAIL" },
+            check
+        )?;
+    }
+    let failed = report.failures().len();
+    writeln!(
+        out,
+        "\n{} checks in {} cases from {}: {} passed, {} failed",
+        report.checks.len(),
+        cases.len(),
+        path.display(),
+        report.passed(),
+        failed
+    )?;
+    if failed > 0 {
+        return Err(format!("{} formula checks failed", failed).into());
+    }
+    Ok(())
+}
+
+/// List the versions in the plan directory, marking the one that would be used
+fn run_versions(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let dir = cli
+        .plans
+        .as_ref()
+        .ok_or("the versions command needs a plan directory, given with --plans <DIR>")?;
+    let catalog = PlanCatalog::open(dir)?;
+    let selected = select_plan_version(&catalog, cli)
+        .ok()
+        .map(|v| v.id.as_str());
+
+    let mut out = io::stdout().lock();
+    writeln!(
+        out,
+        "  {:<16} {:<15} DESCRIPTION",
+        "VERSION", "EFFECTIVE FROM"
+    )?;
+    for version in catalog.versions() {
+        writeln!(
+            out,
+            "{} {:<16} {:<15} {}",
+            if selected == Some(version.id.as_str()) {
+                '*'
+            } else {
+                ' '
+            },
+            version.id,
+            version.effective_from.to_string(),
+            version.description
+        )?;
+    }
+    match (&cli.plan_version, selected) {
+        (Some(id), _) => writeln!(out, "\n* selected with --plan-version {}", id)?,
+        (None, Some(_)) => writeln!(out, "\n* effective on {}", quote_date(cli))?,
+        (None, None) => writeln!(out, "\nno version is effective on {}", quote_date(cli))?,
+    }
+    Ok(())
+}
 
-    println!("Loaded {} applicants", applicant_count);
+/// Load applicants from repository, setting aside those that fail validation
+/// In lenient mode unparseable rows are skipped and reported, within the error budget
+fn load_applicants(
+    app: &PremiumCalculationApp,
+    cli: &Cli,
+) -> Result<Vec<Applicant>, Box<dyn Error>> {
+    let applicants = if cli.lenient {
+        let report = app.load_applicants_lenient()?;
+        log_skipped_rows(&report.skipped, report.skipped.len(), report.rows_read);
+        if let Some(max_bad_rows) = cli.max_bad_rows {
+            report.check_error_budget(max_bad_rows / 100.0)?;
+        }
+        report.applicants
+    } else {
+        app.load_applicants()?
+    };
+    info!("Loaded {} applicants", applicants.len());
+
+    let report = app.validate_applicants(applicants);
+    log_rejections(
+        &report.rejected,
+        report.rejected.len(),
+        counts_by_rule(&report.rejected),
+    );
+    let applicants = report.valid;
 
     // Warn about large datasets in debug mode
     #[cfg(debug_assertions)]
-    if applicant_count > 1000 {
-        eprintln!(
-            "\n⚠️  WARNING: Running {} applicants in DEBUG mode will be very slow!",
-            applicant_count
+    if applicants.len() > 1000 {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        warn!(
+            "Running {} applicants in DEBUG mode will be very slow!",
+            applicants.len()
         );
-        eprintln!("   For large datasets, use RELEASE mode:");
-        eprintln!(
-            "   cargo run --release{}\n",
-            if benchmark_mode {
-                " -- --benchmark"
-            } else {
-                ""
-            }
+        warn!(
+            "For large datasets, use RELEASE mode: cargo run --release -- {}",
+            args.join(" ")
         );
-        eprintln!("   Press Ctrl+C to cancel, or wait for debug build to complete...\n");
+        warn!("Press Ctrl+C to cancel, or wait for debug build to complete...");
     }
 
-    println!("Calculating premiums in parallel...");
+    Ok(applicants)
+}
+
+/// Calculate all premiums in parallel, logging timings, warnings and failures
+fn calculate(
+    app: &PremiumCalculationApp,
+    applicants: Vec<Applicant>,
+) -> Result<CalculationBatch, Box<dyn Error>> {
+    let applicant_count = applicants.len();
+    info!("Calculating premiums in parallel...");
 
-    // Calculate all premiums in parallel
-    let (results, total_time) = app.calculate_all_premiums(applicants);
+    let batch = app.calculate_all_premiums(applicants)?;
+    let total_ms = batch.duration.as_secs_f64() * 1000.0;
 
-    println!(
+    info!(
         "Calculated {} premiums in {:.2}ms",
-        results.len(),
-        total_time.as_secs_f64() * 1000.0
+        batch.results.len(),
+        total_ms
     );
-    println!(
+    info!(
         "Average time per calculation: {:.2}ms",
-        total_time.as_secs_f64() * 1000.0 / results.len() as f64
+        total_ms / applicant_count as f64
     );
 
-    // If in benchmark mode, exit without launching UI
-    if benchmark_mode {
-        println!("\nBenchmark complete!");
-        return Ok(());
+    let flagged = batch
+        .results
+        .iter()
+        .filter(|result| !result.warnings.is_empty())
+        .count();
+    if flagged > 0 {
+        warn!("{} premiums carry warnings", flagged);
+    }
+    log_failures(&batch.failures, batch.failures.len());
+
+    Ok(batch)
+}
+
+fn log_skipped_rows(skipped: &[RowError], count: usize, rows_read: usize) {
+    if count > 0 {
+        warn!(
+            "Skipped {} of {} rows that could not be read:",
+            count, rows_read
+        );
+        log_first(skipped, count);
+    }
+}
+
+fn log_rejections(
+    rejected: &[Rejection],
+    count: usize,
+    by_rule: impl IntoIterator<Item = (impl Display, usize)>,
+) {
+    if count > 0 {
+        warn!("Rejected {} applicants that failed validation:", count);
+        for (rule, count) in by_rule {
+            warn!("  {:<24} {}", rule, count);
+        }
+        log_first(rejected, count);
+    }
+}
+
+fn log_failures(failures: &[CalculationFailure], count: usize) {
+    if count > 0 {
+        warn!("Failed to calculate {} premiums:", count);
+        log_first(failures, count);
+    }
+}
+
+/// Log the first few of `count` diagnostics and how many more there are
+fn log_first(items: &[impl Display], count: usize) {
+    for item in items.iter().take(5) {
+        warn!("  {}", item);
+    }
+    if count > 5 {
+        warn!("  ... and {} more", count - 5);
+    }
+}
+
+/// Rate all applicants and browse the results in the terminal UI
+/// Formulas loaded from a directory are watched and re-rated whenever a script changes
+fn run_tui(app: &mut PremiumCalculationApp, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let applicants = load_applicants(app, cli)?;
+    let batch = calculate(app, applicants.clone())?;
+    let state = AppState::new(batch.results, batch.failures, batch.duration)
+        .with_sub_factors(batch.sub_factors);
+
+    let Some(directory) = app.formula_directory() else {
+        return browse(state, |_| {});
+    };
+    let mut watcher = FormulaWatcher::new(directory);
+    browse(state, |state| {
+        if watcher.changed() {
+            state.banner = Some(reload(app, &applicants, state));
+        }
+    })
+}
+
+/// Recompile the formulas and rate the applicants again, keeping the previous results on error
+/// Nothing is logged, as that would garble the terminal UI
+fn reload(
+    app: &mut PremiumCalculationApp,
+    applicants: &[Applicant],
+    state: &mut AppState,
+) -> Banner {
+    match app.reload_formulas(applicants) {
+        Ok(batch) => {
+            let message = format!(
+                "Reloaded {} formulas: {} applicants rated in {:.2}ms{}",
+                app.formula_names().len(),
+                batch.results.len(),
+                batch.duration.as_secs_f64() * 1000.0,
+                if batch.failures.is_empty() {
+                    String::new()
+                } else {
+                    format!(", {} failed", batch.failures.len())
+                }
+            );
+            state.replace_results(batch.results, batch.failures, batch.duration);
+            state.sub_factors = batch.sub_factors;
+            Banner {
+                message,
+                is_error: false,
+            }
+        }
+        Err(e) => Banner {
+            message: format!(
+                "Reload failed, keeping previous formulas and results: {}",
+                e
+            ),
+            is_error: true,
+        },
     }
+}
 
+/// Show results in the terminal UI until the user quits
+/// `on_tick` runs between input polls and may update the state
+fn browse(mut state: AppState, on_tick: impl FnMut(&mut AppState)) -> Result<(), Box<dyn Error>> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -86,9 +455,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create UI state and run
-    let mut state = AppState::new(results, total_time);
-    let res = run_ui(&mut terminal, &mut state);
+    let res = run_ui(&mut terminal, &mut state, on_tick);
 
     // Restore terminal
     disable_raw_mode()?;
@@ -106,11 +473,414 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Rate all applicants with the current and the candidate formula set and report the changes
+fn run_compare(
+    app: &PremiumCalculationApp,
+    cli: &Cli,
+    args: &CompareArgs,
+) -> Result<(), Box<dyn Error>> {
+    let candidate: Box<dyn FormulaRepository> = match (&args.candidate, &args.candidate_version) {
+        (Some(dir), _) => Box::new(FileFormulaRepository::new(dir)),
+        (None, Some(id)) => {
+            let dir = cli
+                .plans
+                .as_ref()
+                .ok_or("--candidate-version needs --plans")?;
+            let catalog = PlanCatalog::open(dir)?;
+            Box::new(catalog.repository(catalog.get(id)?))
+        }
+        (None, None) => return Err("compare needs --candidate or --candidate-version".into()),
+    };
+
+    let applicants = load_applicants(app, cli)?;
+    info!(
+        "Rating {} applicants with both formula sets...",
+        applicants.len()
+    );
+    let batch = app.compare_premiums(candidate, applicants)?;
+    log_failures(&batch.baseline.failures, batch.baseline.failures.len());
+    log_failures(&batch.candidate.failures, batch.candidate.failures.len());
+
+    if let Some(path) = &args.output {
+        write_premium_changes(File::create(path)?, &batch.comparison)?;
+        info!(
+            "Wrote {} premium changes to {}",
+            batch.comparison.changes.len(),
+            path.display()
+        );
+    }
+
+    if args.tui {
+        let state = AppState::new(
+            batch.candidate.results,
+            batch.candidate.failures,
+            batch.candidate.duration,
+        )
+        .with_sub_factors(batch.candidate.sub_factors)
+        .with_comparison(&batch.comparison);
+        return browse(state, |_| {});
+    }
+
+    print_comparison(&mut io::stdout().lock(), &batch.comparison, args.top)?;
+    Ok(())
+}
+
+/// Every applicant's old and new monthly premium as CSV
+fn write_premium_changes(
+    output: impl Write,
+    comparison: &PremiumComparison,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record([
+        "id",
+        "name",
+        "baseline_premium",
+        "candidate_premium",
+        "delta",
+        "percent_change",
+    ])?;
+    for change in &comparison.changes {
+        writer.write_record([
+            change.applicant_id.to_string(),
+            change.name.clone(),
+            format!("{:.2}", change.baseline),
+            format!("{:.2}", change.candidate),
+            format!("{:.2}", change.delta()),
+            change
+                .percent_change()
+                .map(|percent| format!("{:.2}", percent))
+                .unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Summary, distribution and biggest movers of a rate change
+fn print_comparison(
+    writer: &mut impl Write,
+    comparison: &PremiumComparison,
+    top: usize,
+) -> io::Result<()> {
+    let percent = |value: Option<f64>| match value {
+        Some(value) => format!("{:+.2}%", value),
+        None => "n/a".to_string(),
+    };
+    let (baseline_total, candidate_total) = comparison.totals();
+
+    writeln!(
+        writer,
+        "Comparing {} -> {} over {} applicants",
+        comparison.baseline_version,
+        comparison.candidate_version,
+        comparison.changes.len()
+    )?;
+    writeln!(
+        writer,
+        "  {:<24}${:.2} -> ${:.2} ({})",
+        "Total Monthly Premium",
+        baseline_total,
+        candidate_total,
+        percent(
+            (baseline_total != 0.0)
+                .then(|| { (candidate_total - baseline_total) / baseline_total * 100.0 })
+        )
+    )?;
+    writeln!(
+        writer,
+        "  {:<24}{} up, {} down, {} unchanged",
+        "Applicants",
+        comparison.increased(),
+        comparison.decreased(),
+        comparison.unchanged()
+    )?;
+    writeln!(
+        writer,
+        "  {:<24}{}",
+        "Mean Change",
+        percent(comparison.mean_percent_change())
+    )?;
+    writeln!(
+        writer,
+        "  {:<24}{}",
+        "Median Change",
+        percent(comparison.percentile(50.0))
+    )?;
+    writeln!(
+        writer,
+        "  {:<24}{} to {}",
+        "5th-95th Percentile",
+        percent(comparison.percentile(5.0)),
+        percent(comparison.percentile(95.0))
+    )?;
+
+    writeln!(writer, "\nDistribution of changes")?;
+    let buckets = comparison.distribution();
+    let largest = buckets.iter().map(|bucket| bucket.count).max().unwrap_or(0);
+    for bucket in &buckets {
+        let bar = if largest == 0 {
+            0
+        } else {
+            (bucket.count * 40).div_ceil(largest)
+        };
+        let line = format!(
+            "  {:<16}{:>8}  {}",
+            bucket.label(),
+            bucket.count,
+            "█".repeat(bar)
+        );
+        writeln!(writer, "{}", line.trim_end())?;
+    }
+
+    let movers = comparison.biggest_movers(top);
+    if !movers.is_empty() {
+        writeln!(writer, "\nBiggest movers")?;
+        for change in movers {
+            writeln!(
+                writer,
+                "  {:>6}  {:<24}{:>10} -> {:>10}  {}",
+                change.applicant_id,
+                change.name,
+                format!("${:.2}", change.baseline),
+                format!("${:.2}", change.candidate),
+                percent(change.percent_change())
+            )?;
+        }
+    }
+
+    for (label, ids) in [
+        ("Only rated by the baseline", &comparison.baseline_only),
+        ("Only rated by the candidate", &comparison.candidate_only),
+    ] {
+        if !ids.is_empty() {
+            writeln!(writer, "\n{}: {} applicants", label, ids.len())?;
+        }
+    }
+    Ok(())
+}
+
+/// Rate all applicants and write one row per applicant
+fn run_batch(
+    app: &PremiumCalculationApp,
+    cli: &Cli,
+    args: &BatchArgs,
+) -> Result<(), Box<dyn Error>> {
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let mut writer = result_writer(args.format, output, app.formula_names());
+
+    let rows = match args.chunk_size {
+        Some(chunk_size) => stream_batch(app, cli, chunk_size, writer.as_mut())?,
+        None => {
+            let batch = calculate(app, load_applicants(app, cli)?)?;
+            export::write_all(writer.as_mut(), &batch.results)?
+        }
+    };
+
+    if let Some(path) = &args.output {
+        info!("Wrote {} premiums to {}", rows, path.display());
+    }
+    Ok(())
+}
+
+/// Stream the input through the calculation in chunks, writing results as each chunk is rated
+/// Diagnostics are only known once the whole input is read, so they are logged at the end;
+/// the error budget is checked as each chunk is read, stopping the run once it is exceeded
+fn stream_batch(
+    app: &PremiumCalculationApp,
+    cli: &Cli,
+    chunk_size: usize,
+    writer: &mut dyn ResultWriter,
+) -> Result<usize, Box<dyn Error>> {
+    info!("Streaming premiums in chunks of {} rows...", chunk_size);
+    let max_error_rate = cli.max_bad_rows.map(|max_bad_rows| max_bad_rows / 100.0);
+    let summary = app.stream_premiums(chunk_size, cli.lenient, max_error_rate, writer)?;
+
+    info!(
+        "Calculated {} premiums from {} rows in {:.2}ms",
+        summary.calculated,
+        summary.rows_read,
+        summary.duration.as_secs_f64() * 1000.0
+    );
+    log_skipped_rows(
+        &summary.skipped.first,
+        summary.skipped.count,
+        summary.rows_read,
+    );
+    log_rejections(
+        &summary.rejected.first,
+        summary.rejected.count,
+        summary.rejected_by_rule,
+    );
+    log_failures(&summary.failures.first, summary.failures.count);
+
+    Ok(summary.calculated)
+}
+
+/// Writer for the requested format, with one column per formula output
+fn result_writer(
+    format: OutputFormat,
+    output: Box<dyn Write>,
+    formula_names: Vec<String>,
+) -> Box<dyn ResultWriter> {
+    match format {
+        OutputFormat::Table => Box::new(TableResultWriter::new(output)),
+        OutputFormat::Csv => Box::new(CsvResultWriter::new(output, formula_names)),
+        OutputFormat::Json => Box::new(JsonResultWriter::new(output, formula_names)),
+        OutputFormat::Jsonl => Box::new(JsonLinesResultWriter::new(output, formula_names)),
+    }
+}
+
+/// Rate all applicants and compare throughput against recompiling formulas per applicant
+fn run_benchmark(app: &PremiumCalculationApp, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let applicants = load_applicants(app, cli)?;
+    let applicant_count = applicants.len();
+
+    info!("Calculating baseline with formulas recompiled per applicant...");
+    let (baseline, baseline_time) = app.calculate_all_premiums_uncompiled(&applicants);
+    let batch = calculate(app, applicants)?;
+
+    println!(
+        "Rated {} applicants: {} calculated, {} failed",
+        applicant_count,
+        batch.results.len(),
+        batch.failures.len()
+    );
+    println!("\nThroughput:");
+    print_throughput("Recompiled per applicant", baseline.len(), baseline_time);
+    print_throughput("Compiled rating plan", batch.results.len(), batch.duration);
+    println!(
+        "Speedup: {:.1}x",
+        baseline_time.as_secs_f64() / batch.duration.as_secs_f64()
+    );
+    println!("\nBenchmark complete!");
+    Ok(())
+}
+
+/// Quote a single applicant given as flags, as JSON on stdin or by id from the input file
+fn run_quote(app: &PremiumCalculationApp, args: &QuoteArgs) -> Result<(), Box<dyn Error>> {
+    let applicant = if let Some(id) = args.from_input {
+        app.load_applicants()?
+            .into_iter()
+            .find(|applicant| applicant.id == id)
+            .ok_or(format!("applicant {} not found", id))?
+    } else if args.stdin {
+        serde_json::from_reader(io::stdin().lock())
+            .map_err(|e| format!("invalid applicant JSON on stdin: {}", e))?
+    } else {
+        args.applicant.to_applicant()?
+    };
+
+    let result = app.calculate_premium(&applicant)?;
+    match args.format {
+        OutputFormat::Table => print_quote(&mut io::stdout().lock(), &result, app.sub_factors())?,
+        format => {
+            let output = Box::new(BufWriter::new(io::stdout().lock()));
+            let mut writer = result_writer(format, output, app.formula_names());
+            export::write_all(writer.as_mut(), [&result])?;
+        }
+    }
+    Ok(())
+}
+
+fn print_quote(
+    writer: &mut impl Write,
+    result: &PremiumResult,
+    sub_factors: &BTreeMap<String, String>,
+) -> io::Result<()> {
+    if result.applicant.name.is_empty() {
+        writeln!(writer, "Quote")?;
+    } else {
+        writeln!(
+            writer,
+            "Quote for {} (applicant {})",
+            result.applicant.name, result.applicant.id
+        )?;
+    }
+    writeln!(writer, "  {:<24}{}", "Plan Version", result.plan_version)?;
+    writeln!(
+        writer,
+        "  {:<24}${:.2}",
+        "Base Premium", result.base_premium
+    )?;
+    for (label, value) in ui::factor_breakdown(result, sub_factors) {
+        writeln!(writer, "  {:<24}x{:.3}", label, value)?;
+    }
+    writeln!(
+        writer,
+        "  {:<24}${:.2}/mo before modal factors",
+        "Rated Premium", result.final_premium
+    )?;
+    for option in &result.payment_options {
+        writeln!(
+            writer,
+            "  {:<24}{:>2} x ${:.2} = ${:.2}/yr",
+            format!("{} Payment", option.mode.label()),
+            option.mode.installments_per_year(),
+            option.installment,
+            option.annual_total
+        )?;
+    }
+    let affordability = &result.underwriting.affordability;
+    writeln!(
+        writer,
+        "  {:<24}${:.2}",
+        "Max Coverage", affordability.max_coverage
+    )?;
+    writeln!(
+        writer,
+        "  {:<24}${:.2}",
+        "Max Annual Premium", affordability.max_annual_premium
+    )?;
+    writeln!(
+        writer,
+        "  {:<24}{}",
+        "Decision", result.underwriting.decision
+    )?;
+    for reason in &result.underwriting.reasons {
+        writeln!(writer, "    - {}", reason)?;
+    }
+    for warning in &result.warnings {
+        writeln!(writer, "  ⚠ {}", warning)?;
+    }
+    if !result.trace.is_empty() {
+        writeln!(writer, "\nExplanation")?;
+    }
+    for trace in &result.trace {
+        writeln!(
+            writer,
+            "  {:<24}{}",
+            ui::formula_label(&trace.formula),
+            trace.output
+        )?;
+        if let Some(branch) = trace.branch_label() {
+            writeln!(writer, "    when   {}", branch)?;
+        }
+        if !trace.inputs.is_empty() {
+            writeln!(writer, "    read   {}", trace.inputs_label())?;
+        }
+    }
+    Ok(())
+}
+
+fn print_throughput(label: &str, count: usize, duration: Duration) {
+    println!(
+        "  {:<26} {:>10.2}ms  {:>12.0} calculations/s",
+        label,
+        duration.as_secs_f64() * 1000.0,
+        count as f64 / duration.as_secs_f64()
+    );
+}
+
 fn run_ui(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     state: &mut AppState,
+    mut on_tick: impl FnMut(&mut AppState),
 ) -> io::Result<()> {
     loop {
+        on_tick(state);
         terminal.draw(|f| ui::render(f, state))?;
 
         if event::poll(Duration::from_millis(100))? {
@@ -1,10 +1,91 @@
-use crate::domain::Applicant;
+use crate::domain::{Applicant, RowError};
+use csv::StringRecord;
 use std::error::Error;
+use std::fs::File;
+
+/// A parsed applicant, or the reason its row could not be read
+pub type ApplicantRow = Result<Applicant, RowError>;
+
+/// Rows in input order; the outer error is an I/O failure that ends the stream
+pub type ApplicantRows<'a> = Box<dyn Iterator<Item = Result<ApplicantRow, Box<dyn Error>>> + 'a>;
 
 /// Repository for loading applicant data
 /// Follows Interface Segregation Principle and Dependency Inversion Principle
 pub trait ApplicantRepository {
     fn load_all(&self) -> Result<Vec<Applicant>, Box<dyn Error>>;
+
+    /// Read applicants one row at a time, so callers can process inputs larger than memory
+    fn rows(&self) -> Result<ApplicantRows<'_>, Box<dyn Error>> {
+        Ok(Box::new(self.load_all()?.into_iter().map(|a| Ok(Ok(a)))))
+    }
+
+    /// Load every readable applicant, collecting the rows that cannot be parsed
+    /// instead of failing on the first one
+    fn load_lenient(&self) -> Result<LoadReport, Box<dyn Error>> {
+        let mut report = LoadReport {
+            applicants: Vec::new(),
+            skipped: Vec::new(),
+            rows_read: 0,
+        };
+        for row in self.rows()? {
+            report.rows_read += 1;
+            match row? {
+                Ok(applicant) => report.applicants.push(applicant),
+                Err(e) => report.skipped.push(e),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Outcome of a lenient load
+#[derive(Debug)]
+pub struct LoadReport {
+    pub applicants: Vec<Applicant>,
+    pub skipped: Vec<RowError>,
+    pub rows_read: usize,
+}
+
+impl LoadReport {
+    /// Fraction of the rows read that were skipped
+    pub fn error_rate(&self) -> f64 {
+        error_rate(self.skipped.len(), self.rows_read)
+    }
+
+    /// Fail when more than `max_error_rate` of the rows (0.01 for 1%) were skipped
+    pub fn check_error_budget(&self, max_error_rate: f64) -> Result<(), Box<dyn Error>> {
+        check_error_budget(self.skipped.len(), self.rows_read, max_error_rate)
+    }
+}
+
+/// Fraction of `rows_read` that were skipped
+fn error_rate(skipped: usize, rows_read: usize) -> f64 {
+    if rows_read == 0 {
+        0.0
+    } else {
+        skipped as f64 / rows_read as f64
+    }
+}
+
+/// Fail when more than `max_error_rate` of the rows read (0.01 for 1%) were skipped
+pub fn check_error_budget(
+    skipped: usize,
+    rows_read: usize,
+    max_error_rate: f64,
+) -> Result<(), Box<dyn Error>> {
+    let rate = error_rate(skipped, rows_read);
+    if rate > max_error_rate {
+        return Err(format!(
+            "{} of {} rows could not be read ({:.2}%), above the error budget of {:.2}%",
+            skipped,
+            rows_read,
+            rate * 100.0,
+            max_error_rate * 100.0
+        )
+        .into());
+    }
+    Ok(())
 }
 
 /// CSV-based implementation of ApplicantRepository
@@ -18,16 +99,217 @@ impl CsvApplicantRepository {
     }
 }
 
+/// Lazily parses a CSV file, holding only the current record in memory
+struct CsvApplicantRows {
+    reader: csv::Reader<File>,
+    headers: StringRecord,
+    record: StringRecord,
+    done: bool,
+}
+
+impl CsvApplicantRows {
+    fn parse_row(&self) -> ApplicantRow {
+        let row_error = |column: Option<String>, message: String| RowError {
+            line: self
+                .record
+                .position()
+                .map(|pos| pos.line())
+                .unwrap_or_default(),
+            column,
+            raw: raw_text(&self.record),
+            message,
+        };
+
+        if self.record.len() != self.headers.len() {
+            return Err(row_error(
+                None,
+                format!(
+                    "expected {} fields, found {}",
+                    self.headers.len(),
+                    self.record.len()
+                ),
+            ));
+        }
+
+        self.record.deserialize(Some(&self.headers)).map_err(|e| {
+            let (column, message) = match e.kind() {
+                csv::ErrorKind::Deserialize { err, .. } => (
+                    err.field()
+                        .and_then(|index| self.headers.get(index as usize))
+                        .map(str::to_string),
+                    err.kind().to_string(),
+                ),
+                _ => (None, e.to_string()),
+            };
+            row_error(column, message)
+        })
+    }
+}
+
+impl Iterator for CsvApplicantRows {
+    type Item = Result<ApplicantRow, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.reader.read_record(&mut self.record) {
+            Ok(true) => Some(Ok(self.parse_row())),
+            Ok(false) => {
+                self.done = true;
+                None
+            }
+            Err(e) if matches!(e.kind(), csv::ErrorKind::Io(_)) => {
+                self.done = true;
+                Some(Err(e.into()))
+            }
+            // Malformed CSV such as invalid UTF-8 leaves no usable record text
+            Err(e) => Some(Ok(Err(RowError {
+                line: e.position().map(|pos| pos.line()).unwrap_or_default(),
+                column: None,
+                raw: String::new(),
+                message: e.to_string(),
+            }))),
+        }
+    }
+}
+
+/// A record written back as one CSV line, quoting fields where needed
+fn raw_text(record: &StringRecord) -> String {
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    let _ = writer.write_record(record);
+    let bytes = writer.into_inner().unwrap_or_default();
+    String::from_utf8_lossy(&bytes).trim_end().to_string()
+}
+
 impl ApplicantRepository for CsvApplicantRepository {
     fn load_all(&self) -> Result<Vec<Applicant>, Box<dyn Error>> {
-        let mut rdr = csv::Reader::from_path(&self.file_path)?;
         let mut applicants = Vec::new();
-
-        for result in rdr.deserialize() {
-            let applicant: Applicant = result?;
-            applicants.push(applicant);
+        for row in self.rows()? {
+            applicants.push(row?.map_err(|e| format!("{}: {}", self.file_path, e))?);
         }
 
         Ok(applicants)
     }
+
+    fn rows(&self) -> Result<ApplicantRows<'_>, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(&self.file_path)
+            .map_err(|e| format!("failed to open {}: {}", self.file_path, e))?;
+        let headers = reader.headers()?.clone();
+
+        Ok(Box::new(CsvApplicantRows {
+            reader,
+            headers,
+            record: StringRecord::new(),
+            done: false,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    const HEADER: &str = "id,name,age,gender,smoker,occupation,annual_income,bmi,blood_pressure_sys,blood_pressure_dia,cholesterol,existing_conditions,family_history_score,coverage_amount,coverage_years";
+
+    fn write_csv(name: &str, rows: &[&str]) -> CsvApplicantRepository {
+        let path = temp_dir(&format!("applicants_{}", name)).join("applicants.csv");
+        let mut contents = format!("{}\n", HEADER);
+        for row in rows {
+            contents.push_str(row);
+            contents.push('\n');
+        }
+        std::fs::write(&path, contents).unwrap();
+        CsvApplicantRepository::new(path.to_string_lossy().into_owned())
+    }
+
+    const GOOD_ROW: &str = "1,Jane Doe,40,F,false,Nurse,65000,24.5,125,82,200,none,2,250000,20";
+
+    #[test]
+    fn test_load_all_reads_rows() {
+        let repository = write_csv("good", &[GOOD_ROW]);
+        let applicants = repository.load_all().unwrap();
+        assert_eq!(applicants.len(), 1);
+        assert_eq!(applicants[0].name, "Jane Doe");
+    }
+
+    #[test]
+    fn test_load_all_names_line_and_column() {
+        let repository = write_csv(
+            "strict",
+            &[
+                GOOD_ROW,
+                "2,John,forty,M,false,Clerk,50000,22,120,80,180,none,1,300000,10",
+            ],
+        );
+        let err = repository.load_all().unwrap_err().to_string();
+        assert!(err.contains("line 3"), "{}", err);
+        assert!(err.contains("'age'"), "{}", err);
+    }
+
+    #[test]
+    fn test_lenient_load_skips_bad_rows() {
+        let repository = write_csv(
+            "lenient",
+            &[
+                GOOD_ROW,
+                "2,John,forty,M,false,Clerk,50000,22,120,80,180,none,1,300000,10",
+                "3,\"Short, Row\",50",
+                "4,Ann,50,F,false,Clerk,50000,22,120,80,180,none,1,300000,10",
+            ],
+        );
+        let report = repository.load_lenient().unwrap();
+
+        assert_eq!(report.rows_read, 4);
+        let ids: Vec<u32> = report.applicants.iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec![1, 4]);
+
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!(report.skipped[0].line, 3);
+        assert_eq!(report.skipped[0].column.as_deref(), Some("age"));
+        assert!(report.skipped[0].raw.starts_with("2,John,forty"));
+        assert_eq!(report.skipped[1].line, 4);
+        assert_eq!(report.skipped[1].column, None);
+        assert_eq!(report.skipped[1].raw, "3,\"Short, Row\",50");
+        assert!(report.skipped[1].message.contains("expected 15 fields"));
+    }
+
+    #[test]
+    fn test_rows_are_read_lazily_in_order() {
+        let repository = write_csv(
+            "rows",
+            &[
+                GOOD_ROW,
+                "2,John,forty,M,false,Clerk,50000,22,120,80,180,none,1,300000,10",
+                "3,Ann,50,F,false,Clerk,50000,22,120,80,180,none,1,300000,10",
+            ],
+        );
+        let mut rows = repository.rows().unwrap();
+
+        assert_eq!(rows.next().unwrap().unwrap().unwrap().id, 1);
+        assert_eq!(rows.next().unwrap().unwrap().unwrap_err().line, 3);
+        assert_eq!(rows.next().unwrap().unwrap().unwrap().id, 3);
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn test_error_budget() {
+        let repository = write_csv(
+            "budget",
+            &[
+                GOOD_ROW,
+                "2,John,forty,M,false,Clerk,50000,22,120,80,180,none,1,300000,10",
+            ],
+        );
+        let report = repository.load_lenient().unwrap();
+
+        assert_eq!(report.error_rate(), 0.5);
+        assert!(report.check_error_budget(0.5).is_ok());
+        assert!(report.check_error_budget(0.01).is_err());
+    }
 }
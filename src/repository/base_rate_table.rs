@@ -0,0 +1,143 @@
+use crate::domain::{Gender, SmokerStatus};
+use serde::Deserialize;
+use std::error::Error;
+use std::io::Read;
+
+/// Built-in base rates, shipped as `data/base_rates.csv`
+const DEFAULT_BASE_RATES: &str = include_str!("../../data/base_rates.csv");
+
+/// One row of the rate table; age and term bands are inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+struct BaseRate {
+    age_min: u32,
+    age_max: u32,
+    gender: Gender,
+    smoker: SmokerStatus,
+    term_min: u32,
+    term_max: u32,
+    rate: f64,
+}
+
+impl BaseRate {
+    fn covers(&self, age: u32, gender: Gender, smoker: SmokerStatus, term: u32) -> bool {
+        self.gender == gender
+            && self.smoker == smoker
+            && (self.age_min..=self.age_max).contains(&age)
+            && (self.term_min..=self.term_max).contains(&term)
+    }
+
+    fn overlaps(&self, other: &BaseRate) -> bool {
+        self.gender == other.gender
+            && self.smoker == other.smoker
+            && self.age_min <= other.age_max
+            && other.age_min <= self.age_max
+            && self.term_min <= other.term_max
+            && other.term_min <= self.term_max
+    }
+}
+
+/// Monthly base rates per 1000 of coverage, by age band, gender, smoker status and term
+#[derive(Debug, Clone)]
+pub struct BaseRateTable {
+    rates: Vec<BaseRate>,
+}
+
+impl BaseRateTable {
+    /// Load a table from a CSV file with
+    /// `age_min,age_max,gender,smoker,term_min,term_max,rate` columns
+    pub fn from_csv(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(file_path)
+            .map_err(|e| format!("failed to open base rate table {}: {}", file_path, e))?;
+        Self::from_reader(file).map_err(|e| format!("{}: {}", file_path, e).into())
+    }
+
+    fn from_reader(reader: impl Read) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = csv::Reader::from_reader(reader);
+        let mut rates: Vec<BaseRate> = Vec::new();
+
+        for (index, result) in rdr.deserialize().enumerate() {
+            let row: BaseRate = result?;
+            // Header is line 1
+            let line = index + 2;
+            if row.age_min > row.age_max || row.term_min > row.term_max {
+                return Err(format!("line {}: band minimum is above its maximum", line).into());
+            }
+            if row.rate <= 0.0 {
+                return Err(format!("line {}: rate {} is not positive", line, row.rate).into());
+            }
+            if rates.iter().any(|existing| existing.overlaps(&row)) {
+                return Err(format!("line {}: overlaps an earlier row", line).into());
+            }
+            rates.push(row);
+        }
+
+        Ok(Self { rates })
+    }
+
+    /// Rate for an applicant, or `None` if no band covers them
+    pub fn rate(&self, age: u32, gender: Gender, smoker: SmokerStatus, term: u32) -> Option<f64> {
+        self.rates
+            .iter()
+            .find(|row| row.covers(age, gender, smoker, term))
+            .map(|row| row.rate)
+    }
+}
+
+impl Default for BaseRateTable {
+    fn default() -> Self {
+        Self::from_reader(DEFAULT_BASE_RATES.as_bytes()).expect("built-in base rate table is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "age_min,age_max,gender,smoker,term_min,term_max,rate\n";
+
+    #[test]
+    fn test_default_table_covers_accepted_applicants() {
+        let table = BaseRateTable::default();
+        for age in 18..=85 {
+            for term in 1..=40 {
+                for gender in [Gender::Male, Gender::Female] {
+                    for smoker in [SmokerStatus::NonSmoker, SmokerStatus::Smoker] {
+                        assert!(table.rate(age, gender, smoker, term).is_some());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_rates_vary_by_age_smoker_and_term() {
+        let table = BaseRateTable::default();
+        let rate = |age, gender, smoker, term| table.rate(age, gender, smoker, term).unwrap();
+        let standard = rate(35, Gender::Male, SmokerStatus::NonSmoker, 20);
+        assert_eq!(standard, 0.5);
+
+        assert!(rate(35, Gender::Female, SmokerStatus::NonSmoker, 20) < standard);
+        assert!(rate(55, Gender::Male, SmokerStatus::NonSmoker, 20) > standard);
+        assert!(rate(25, Gender::Male, SmokerStatus::NonSmoker, 20) < standard);
+        assert!(rate(35, Gender::Male, SmokerStatus::Smoker, 20) > standard);
+        assert!(rate(35, Gender::Male, SmokerStatus::NonSmoker, 30) > standard);
+        assert!(rate(35, Gender::Male, SmokerStatus::NonSmoker, 5) < standard);
+        assert_eq!(
+            table.rate(90, Gender::Male, SmokerStatus::NonSmoker, 20),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid_tables_are_rejected() {
+        let overlapping = format!("{}18,40,M,no,1,40,0.5\n35,50,M,no,1,40,0.6\n", HEADER);
+        let err = BaseRateTable::from_reader(overlapping.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{}", err);
+
+        let negative = format!("{}18,40,M,no,1,40,-0.5\n", HEADER);
+        assert!(BaseRateTable::from_reader(negative.as_bytes()).is_err());
+
+        let unknown_gender = format!("{}18,40,X,no,1,40,0.5\n", HEADER);
+        assert!(BaseRateTable::from_reader(unknown_gender.as_bytes()).is_err());
+    }
+}
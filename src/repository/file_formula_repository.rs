@@ -0,0 +1,196 @@
+use crate::repository::formula_repository::UNVERSIONED;
+use crate::repository::FormulaRepository;
+use formcalc::parser::Parser;
+use formcalc::Formula;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File extension used for formula scripts
+pub const FORMULA_FILE_EXTENSION: &str = "fc";
+
+/// Error raised when a formula script cannot be loaded
+#[derive(Debug)]
+pub struct FormulaLoadError {
+    pub file: PathBuf,
+    pub formula: String,
+    pub message: String,
+}
+
+impl fmt::Display for FormulaLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to load formula '{}' from {}: {}",
+            self.formula,
+            self.file.display(),
+            self.message
+        )
+    }
+}
+
+impl Error for FormulaLoadError {}
+
+/// File-backed formula repository
+/// Loads one formcalc script per `.fc` file; the file stem is the formula name
+pub struct FileFormulaRepository {
+    directory: PathBuf,
+    version: String,
+}
+
+impl FileFormulaRepository {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            version: UNVERSIONED.to_string(),
+        }
+    }
+
+    /// Record the scripts as the given version of a plan
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// List formula script files in the directory, sorted by file name
+    fn formula_files(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let entries = fs::read_dir(&self.directory).map_err(|e| {
+            format!(
+                "failed to read formula directory {}: {}",
+                self.directory.display(),
+                e
+            )
+        })?;
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_file()
+                && path.extension().and_then(|ext| ext.to_str()) == Some(FORMULA_FILE_EXTENSION)
+            {
+                files.push(path);
+            }
+        }
+        files.sort();
+
+        if files.is_empty() {
+            return Err(format!(
+                "no .{} formula files found in {}",
+                FORMULA_FILE_EXTENSION,
+                self.directory.display()
+            )
+            .into());
+        }
+
+        Ok(files)
+    }
+
+    /// Load and syntax-check a single formula script
+    fn load_formula(&self, path: &Path) -> Result<Formula, FormulaLoadError> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let error = |message: String| FormulaLoadError {
+            file: path.to_path_buf(),
+            formula: name.clone(),
+            message,
+        };
+
+        let body = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        if body.trim().is_empty() {
+            return Err(error("formula body is empty".to_string()));
+        }
+
+        Parser::new(&body)
+            .and_then(|mut parser| parser.parse())
+            .map_err(|e| error(e.to_string()))?;
+
+        Ok(Formula::new(name.as_str(), body))
+    }
+}
+
+impl FormulaRepository for FileFormulaRepository {
+    fn version_id(&self) -> &str {
+        &self.version
+    }
+
+    fn directory(&self) -> Option<&Path> {
+        Some(&self.directory)
+    }
+
+    fn load_all(&self) -> Result<Vec<Formula>, Box<dyn Error>> {
+        self.formula_files()?
+            .iter()
+            .map(|path| self.load_formula(path).map_err(Into::into))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::InMemoryFormulaRepository;
+    use crate::test_support::temp_dir;
+    use formcalc::FormulaT;
+
+    #[test]
+    fn test_shipped_formulas_match_in_memory_set() {
+        let repo = FileFormulaRepository::new(concat!(env!("CARGO_MANIFEST_DIR"), "/formulas"));
+        let mut file_names: Vec<String> = repo
+            .load_all()
+            .unwrap()
+            .iter()
+            .map(|f| f.name().to_string())
+            .collect();
+        let mut memory_names: Vec<String> = InMemoryFormulaRepository::new()
+            .load_all()
+            .unwrap()
+            .iter()
+            .map(|f| f.name().to_string())
+            .collect();
+
+        file_names.sort();
+        memory_names.sort();
+        assert_eq!(file_names, memory_names);
+    }
+
+    #[test]
+    fn test_load_formulas_from_directory() {
+        let dir = temp_dir("load");
+        fs::write(dir.join("a.fc"), "return 10").unwrap();
+        fs::write(dir.join("b.fc"), "return get_output_from('a') * 2").unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let formulas = FileFormulaRepository::new(&dir).load_all().unwrap();
+        assert_eq!(formulas.len(), 2);
+        assert_eq!(formulas[0].name(), "a");
+        assert_eq!(formulas[1].name(), "b");
+        assert_eq!(formulas[1].depends_on(), &["a".to_string()]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_error_names_file_and_formula() {
+        let dir = temp_dir("parse_error");
+        fs::write(dir.join("broken.fc"), "if (age < 30) then return").unwrap();
+
+        let err = FileFormulaRepository::new(&dir)
+            .load_all()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'broken'"));
+        assert!(err.contains("broken.fc"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_directory_is_an_error() {
+        let repo = FileFormulaRepository::new("does/not/exist");
+        assert!(repo.load_all().is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::domain::FormulaCase;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Subdirectory of a formula directory holding its test cases
+pub const CASES_DIRECTORY: &str = "cases";
+
+/// Load formula test cases from a JSON file, or from every `.json` file in a directory
+/// Each file holds a list of cases; files are read in name order
+pub fn load_formula_cases(path: &Path) -> Result<Vec<FormulaCase>, Box<dyn Error>> {
+    if !path.is_dir() {
+        return load_case_file(path);
+    }
+
+    let entries = fs::read_dir(path)
+        .map_err(|e| format!("failed to read case directory {}: {}", path.display(), e))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let file = entry?.path();
+        if file.is_file() && file.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            files.push(file);
+        }
+    }
+    files.sort();
+    if files.is_empty() {
+        return Err(format!("no .json case files found in {}", path.display()).into());
+    }
+
+    let mut cases = Vec::new();
+    for file in files {
+        cases.extend(load_case_file(&file)?);
+    }
+    Ok(cases)
+}
+
+fn load_case_file(path: &Path) -> Result<Vec<FormulaCase>, Box<dyn Error>> {
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("failed to open case file {}: {}", path.display(), e))?;
+    let mut cases: Vec<FormulaCase> =
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let file = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for case in &mut cases {
+        if case.expected.is_empty() {
+            return Err(format!(
+                "{}: case '{}' expects no outputs",
+                path.display(),
+                case.name
+            )
+            .into());
+        }
+        case.file = file.clone();
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::CaseValue;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_load_cases_from_directory() {
+        let dir = temp_dir("cases_load");
+        fs::write(
+            dir.join("b.json"),
+            r#"[{"name": "smoker", "inputs": {"smoker": true, "condition_loading": 1.2}, "expected": {"lifestyle_multiplier": 2.16}}]"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("a.json"),
+            r#"[{"name": "office", "inputs": {"occupation": "Engineer"}, "expected": {"occupation_factor": 1}, "tolerance": 0.01}]"#,
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let cases = load_formula_cases(&dir).unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(
+            (cases[0].file.as_str(), cases[0].name.as_str()),
+            ("a.json", "office")
+        );
+        assert_eq!(
+            cases[0].inputs["occupation"],
+            CaseValue::Text("Engineer".to_string())
+        );
+        assert_eq!(cases[0].tolerance, Some(0.01));
+        assert_eq!(cases[1].inputs["smoker"], CaseValue::Bool(true));
+        assert_eq!(
+            cases[1].expected["lifestyle_multiplier"],
+            CaseValue::Number(2.16)
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_case_files_are_rejected() {
+        let dir = temp_dir("cases_invalid");
+        fs::write(
+            dir.join("typo.json"),
+            r#"[{"name": "a", "input": {"age": 30}, "expected": {"age_factor": 1.2}}]"#,
+        )
+        .unwrap();
+        let err = load_formula_cases(&dir.join("typo.json")).unwrap_err();
+        assert!(err.to_string().contains("typo.json"), "{}", err);
+
+        fs::write(dir.join("typo.json"), r#"[{"name": "a", "expected": {}}]"#).unwrap();
+        let err = load_formula_cases(&dir).unwrap_err();
+        assert!(err.to_string().contains("expects no outputs"), "{}", err);
+
+        fs::remove_dir_all(dir).unwrap();
+        assert!(load_formula_cases(Path::new("does/not/exist")).is_err());
+    }
+}
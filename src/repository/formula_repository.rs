@@ -1,9 +1,26 @@
 use formcalc::Formula;
 use std::error::Error;
+use std::path::Path;
+
+/// Version id of formula sets that are not part of a versioned plan catalog
+pub const UNVERSIONED: &str = "unversioned";
+
+/// Version id of the built-in formula set
+pub const BUILT_IN_VERSION: &str = "built-in";
 
 /// Repository abstraction for loading premium calculation formulas
 pub trait FormulaRepository: Send + Sync {
     fn load_all(&self) -> Result<Vec<Formula>, Box<dyn Error>>;
+
+    /// Id of the formula set's version, recorded on every result
+    fn version_id(&self) -> &str {
+        UNVERSIONED
+    }
+
+    /// Directory the scripts are read from, if they live on disk
+    fn directory(&self) -> Option<&Path> {
+        None
+    }
 }
 
 /// In-memory formula repository that loads formulas as if from a data source
@@ -22,18 +39,21 @@ impl Default for InMemoryFormulaRepository {
 }
 
 impl FormulaRepository for InMemoryFormulaRepository {
+    fn version_id(&self) -> &str {
+        BUILT_IN_VERSION
+    }
+
     fn load_all(&self) -> Result<Vec<Formula>, Box<dyn Error>> {
         Ok(vec![
             self.load_base_premium_formula(),
-            self.load_age_factor_formula(),
             self.load_bmi_risk_formula(),
             self.load_bp_risk_formula(),
             self.load_cholesterol_risk_formula(),
             self.load_health_risk_score_formula(),
+            self.load_condition_loading_formula(),
             self.load_lifestyle_multiplier_formula(),
             self.load_family_history_factor_formula(),
             self.load_occupation_factor_formula(),
-            self.load_duration_discount_formula(),
             self.load_final_premium_formula(),
         ])
     }
@@ -44,26 +64,8 @@ impl InMemoryFormulaRepository {
         Formula::new(
             "base_premium",
             r#"
-            return rnd((coverage_amount / 1000) * 0.5, 2)
-        "#,
-        )
-    }
-
-    fn load_age_factor_formula(&self) -> Formula {
-        Formula::new(
-            "age_factor",
-            r#"
-            if (age < 30) then
-                return 1.0
-            else if (age < 40) then
-                return 1.2
-            else if (age < 50) then
-                return 1.5
-            else if (age < 60) then
-                return 2.0
-            else
-                return 2.8
-            end
+            // Monthly rate per 1000 of coverage from the base rate table
+            return rnd((coverage_amount / 1000) * base_rate(age, gender, smoker, coverage_years), 2)
         "#,
         )
     }
@@ -124,19 +126,31 @@ impl InMemoryFormulaRepository {
         )
     }
 
+    fn load_condition_loading_formula(&self) -> Formula {
+        Formula::new(
+            "condition_loading",
+            r#"
+            // Each existing condition adds its own loading; 'other' stands for any condition without one
+            // Combined loadings are capped so conditions alone never more than double the premium
+            return min(
+                1 +
+                0.6 * diabetes +
+                0.4 * hypertension +
+                0.3 * asthma +
+                0.2 * arthritis +
+                0.6 * other_conditions,
+                2.0
+            )
+        "#,
+        )
+    }
+
     fn load_lifestyle_multiplier_formula(&self) -> Formula {
         Formula::new(
             "lifestyle_multiplier",
             r#"
-            if (smoker and has_conditions) then
-                return 2.5
-            else if (smoker) then
-                return 1.8
-            else if (has_conditions) then
-                return 1.6
-            else
-                return 1.0
-            end
+            // Smoking is priced by the base rate table, so only conditions load the premium here
+            return get_output_from('condition_loading')
         "#,
         )
     }
@@ -157,19 +171,20 @@ impl InMemoryFormulaRepository {
     }
 
     fn load_occupation_factor_formula(&self) -> Formula {
-        Formula::new("occupation_factor", "return 1.0")
-    }
-
-    fn load_duration_discount_formula(&self) -> Formula {
         Formula::new(
-            "duration_discount",
+            "occupation_factor",
             r#"
-            if (coverage_years >= 30) then
-                return 0.95
-            else if (coverage_years >= 20) then
-                return 0.97
-            else
+            if (occupation_class(occupation) = 'office') then
                 return 1.0
+            else if (occupation_class(occupation) = 'light_manual') then
+                return 1.1
+            else if (occupation_class(occupation) = 'heavy_manual') then
+                return 1.25
+            else if (occupation_class(occupation) = 'hazardous') then
+                return 1.5
+            else
+                // Unknown occupations are rated as heavy manual and flagged on the result
+                return 1.25
             end
         "#,
         )
@@ -179,14 +194,13 @@ impl InMemoryFormulaRepository {
         Formula::new(
             "final_premium",
             r#"
+            // Age, gender, smoking and term are priced by the base rate table behind base_premium
             return rnd(
                 get_output_from('base_premium') * 
-                get_output_from('age_factor') * 
                 get_output_from('health_risk_score') * 
                 get_output_from('lifestyle_multiplier') * 
                 get_output_from('family_history_factor') * 
-                get_output_from('occupation_factor') * 
-                get_output_from('duration_discount'),
+                get_output_from('occupation_factor'),
                 2
             )
         "#,
@@ -203,7 +217,7 @@ mod tests {
     fn test_load_all_formulas() {
         let repo = InMemoryFormulaRepository::new();
         let formulas = repo.load_all().unwrap();
-        assert_eq!(formulas.len(), 11);
+        assert_eq!(formulas.len(), 10);
     }
 
     #[test]
@@ -213,15 +227,14 @@ mod tests {
 
         let names: Vec<&str> = formulas.iter().map(|f| f.name()).collect();
         assert!(names.contains(&"base_premium"));
-        assert!(names.contains(&"age_factor"));
         assert!(names.contains(&"bmi_risk"));
         assert!(names.contains(&"bp_risk"));
         assert!(names.contains(&"cholesterol_risk"));
         assert!(names.contains(&"health_risk_score"));
+        assert!(names.contains(&"condition_loading"));
         assert!(names.contains(&"lifestyle_multiplier"));
         assert!(names.contains(&"family_history_factor"));
         assert!(names.contains(&"occupation_factor"));
-        assert!(names.contains(&"duration_discount"));
         assert!(names.contains(&"final_premium"));
     }
 }
@@ -0,0 +1,86 @@
+use crate::repository::file_formula_repository::FORMULA_FILE_EXTENSION;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Modification time and size of every formula script in a directory
+type Snapshot = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// Notices when formula scripts are added, removed or edited by polling their directory
+pub struct FormulaWatcher {
+    directory: PathBuf,
+    snapshot: Snapshot,
+}
+
+impl FormulaWatcher {
+    /// Start watching, taking the scripts as they are now as unchanged
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        let directory = directory.into();
+        let snapshot = Self::scan(&directory);
+        Self {
+            directory,
+            snapshot,
+        }
+    }
+
+    /// Whether any script changed since the last call
+    pub fn changed(&mut self) -> bool {
+        let snapshot = Self::scan(&self.directory);
+        if snapshot == self.snapshot {
+            return false;
+        }
+        self.snapshot = snapshot;
+        true
+    }
+
+    /// A directory that cannot be read counts as empty, so removing it is a change too
+    fn scan(directory: &Path) -> Snapshot {
+        let Ok(entries) = fs::read_dir(directory) else {
+            return Snapshot::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension().and_then(|ext| ext.to_str()) == Some(FORMULA_FILE_EXTENSION)
+            })
+            .filter_map(|path| {
+                let metadata = fs::metadata(&path).ok()?;
+                Some((path, (metadata.modified().ok(), metadata.len())))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_detects_added_edited_and_removed_scripts() {
+        let dir = temp_dir("watch");
+        fs::write(dir.join("a.fc"), "return 1").unwrap();
+        let mut watcher = FormulaWatcher::new(&dir);
+        assert!(!watcher.changed());
+
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        assert!(!watcher.changed());
+
+        // A different size is noticed even when the modification time does not move
+        fs::write(dir.join("a.fc"), "return 10").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::write(dir.join("b.fc"), "return 2").unwrap();
+        assert!(watcher.changed());
+
+        fs::remove_file(dir.join("b.fc")).unwrap();
+        assert!(watcher.changed());
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+    }
+}
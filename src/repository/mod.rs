@@ -1,5 +1,22 @@
 mod applicant_repository;
+mod base_rate_table;
+mod file_formula_repository;
+mod formula_cases;
 mod formula_repository;
+mod formula_watcher;
+mod occupation_table;
+mod plan_catalog;
 
-pub use applicant_repository::{ApplicantRepository, CsvApplicantRepository};
-pub use formula_repository::{FormulaRepository, InMemoryFormulaRepository};
+pub use applicant_repository::{
+    check_error_budget, ApplicantRepository, ApplicantRow, ApplicantRows, CsvApplicantRepository,
+    LoadReport,
+};
+pub use base_rate_table::BaseRateTable;
+pub use file_formula_repository::FileFormulaRepository;
+pub use formula_cases::{load_formula_cases, CASES_DIRECTORY};
+pub use formula_repository::{
+    FormulaRepository, InMemoryFormulaRepository, BUILT_IN_VERSION, UNVERSIONED,
+};
+pub use formula_watcher::FormulaWatcher;
+pub use occupation_table::OccupationRiskTable;
+pub use plan_catalog::{PlanCatalog, PLAN_MANIFEST};
@@ -0,0 +1,96 @@
+use crate::domain::OccupationClass;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Read;
+
+/// Built-in occupation table, shipped as `data/occupation_classes.csv`
+const DEFAULT_OCCUPATION_CLASSES: &str = include_str!("../../data/occupation_classes.csv");
+
+#[derive(Deserialize)]
+struct OccupationRow {
+    occupation: String,
+    risk_class: OccupationClass,
+}
+
+/// Lookup table mapping occupations to their risk class
+/// Occupation names are matched case-insensitively
+#[derive(Debug, Clone)]
+pub struct OccupationRiskTable {
+    classes: HashMap<String, OccupationClass>,
+}
+
+impl OccupationRiskTable {
+    /// Load a table from a CSV file with `occupation,risk_class` columns
+    pub fn from_csv(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(file_path)
+            .map_err(|e| format!("failed to open occupation table {}: {}", file_path, e))?;
+        Self::from_reader(file).map_err(|e| format!("{}: {}", file_path, e).into())
+    }
+
+    fn from_reader(reader: impl Read) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = csv::Reader::from_reader(reader);
+        let mut classes = HashMap::new();
+
+        for result in rdr.deserialize() {
+            let row: OccupationRow = result?;
+            classes.insert(Self::normalize(&row.occupation), row.risk_class);
+        }
+
+        Ok(Self { classes })
+    }
+
+    /// Risk class for an occupation, or `None` if the occupation is not listed
+    pub fn class_of(&self, occupation: &str) -> Option<OccupationClass> {
+        self.classes.get(&Self::normalize(occupation)).copied()
+    }
+
+    fn normalize(occupation: &str) -> String {
+        occupation.trim().to_lowercase()
+    }
+}
+
+impl Default for OccupationRiskTable {
+    fn default() -> Self {
+        Self::from_reader(DEFAULT_OCCUPATION_CLASSES.as_bytes())
+            .expect("built-in occupation table is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_table_covers_generated_occupations() {
+        let table = OccupationRiskTable::default();
+        assert_eq!(table.class_of("Clerk"), Some(OccupationClass::Office));
+        assert_eq!(table.class_of("Nurse"), Some(OccupationClass::LightManual));
+        assert_eq!(
+            table.class_of("Plumber"),
+            Some(OccupationClass::HeavyManual)
+        );
+        assert_eq!(table.class_of("Driver"), Some(OccupationClass::Hazardous));
+    }
+
+    #[test]
+    fn test_lookup_is_case_insensitive() {
+        let table = OccupationRiskTable::default();
+        assert_eq!(
+            table.class_of("  electrician "),
+            Some(OccupationClass::Hazardous)
+        );
+    }
+
+    #[test]
+    fn test_unknown_occupation() {
+        let table = OccupationRiskTable::default();
+        assert_eq!(table.class_of("Astronaut"), None);
+    }
+
+    #[test]
+    fn test_invalid_risk_class_is_rejected() {
+        let csv = "occupation,risk_class\nDiver,extreme\n";
+        assert!(OccupationRiskTable::from_reader(csv.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::domain::PlanVersion;
+use crate::repository::FileFormulaRepository;
+use chrono::NaiveDate;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Manifest listing the versions in a plan directory
+pub const PLAN_MANIFEST: &str = "versions.json";
+
+/// Versioned formula sets kept in one directory
+/// `versions.json` lists every version; the scripts of each live in a subdirectory named after its id
+#[derive(Debug, Clone)]
+pub struct PlanCatalog {
+    directory: PathBuf,
+    /// Sorted by effective date, oldest first
+    versions: Vec<PlanVersion>,
+}
+
+impl PlanCatalog {
+    /// Open a plan directory and check its manifest
+    pub fn open(directory: impl Into<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let directory = directory.into();
+        let manifest = directory.join(PLAN_MANIFEST);
+        let json = std::fs::read_to_string(&manifest)
+            .map_err(|e| format!("failed to open plan manifest {}: {}", manifest.display(), e))?;
+        let versions: Vec<PlanVersion> =
+            serde_json::from_str(&json).map_err(|e| format!("{}: {}", manifest.display(), e))?;
+        Self::new(directory, versions).map_err(|e| format!("{}: {}", manifest.display(), e).into())
+    }
+
+    fn new(directory: PathBuf, mut versions: Vec<PlanVersion>) -> Result<Self, Box<dyn Error>> {
+        if versions.is_empty() {
+            return Err("no plan versions listed".into());
+        }
+        versions.sort_by_key(|version| version.effective_from);
+
+        for (index, version) in versions.iter().enumerate() {
+            if version.id.is_empty() || version.id.contains(['/', '\\']) || version.id == ".." {
+                return Err(format!("'{}' is not a valid version id", version.id).into());
+            }
+            if versions[..index].iter().any(|other| other.id == version.id) {
+                return Err(format!("version '{}' is listed twice", version.id).into());
+            }
+            if index > 0 && versions[index - 1].effective_from == version.effective_from {
+                return Err(format!(
+                    "versions '{}' and '{}' both take effect on {}",
+                    versions[index - 1].id,
+                    version.id,
+                    version.effective_from
+                )
+                .into());
+            }
+            if !directory.join(&version.id).is_dir() {
+                return Err(format!(
+                    "version '{}' has no formula directory {}",
+                    version.id,
+                    directory.join(&version.id).display()
+                )
+                .into());
+            }
+        }
+
+        Ok(Self {
+            directory,
+            versions,
+        })
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Every version, oldest effective date first
+    pub fn versions(&self) -> &[PlanVersion] {
+        &self.versions
+    }
+
+    /// Version with the given id
+    pub fn get(&self, id: &str) -> Result<&PlanVersion, Box<dyn Error>> {
+        self.versions
+            .iter()
+            .find(|version| version.id == id)
+            .ok_or_else(|| {
+                format!(
+                    "unknown plan version '{}', available: {}",
+                    id,
+                    self.versions
+                        .iter()
+                        .map(|version| version.id.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+                .into()
+            })
+    }
+
+    /// Version in force on a quote date: the latest one that took effect on or before it
+    pub fn effective_on(&self, date: NaiveDate) -> Result<&PlanVersion, Box<dyn Error>> {
+        self.versions
+            .iter()
+            .rev()
+            .find(|version| version.effective_from <= date)
+            .ok_or_else(|| {
+                format!(
+                    "no plan version is effective on {}; the earliest takes effect on {}",
+                    date, self.versions[0].effective_from
+                )
+                .into()
+            })
+    }
+
+    /// Formula repository loading the scripts of a version
+    pub fn repository(&self, version: &PlanVersion) -> FileFormulaRepository {
+        FileFormulaRepository::new(self.directory.join(&version.id)).with_version(&version.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::FormulaRepository;
+    use crate::test_support::temp_dir;
+    use std::fs;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    /// A plan directory with one `final_premium` script per version
+    fn plan_dir(name: &str, manifest: &str, ids: &[&str]) -> PathBuf {
+        let dir = temp_dir(name);
+        fs::write(dir.join(PLAN_MANIFEST), manifest).unwrap();
+        for id in ids {
+            fs::create_dir_all(dir.join(id)).unwrap();
+            fs::write(dir.join(id).join("final_premium.fc"), "return 10").unwrap();
+        }
+        dir
+    }
+
+    const MANIFEST: &str = r#"[
+        {"id": "2025-q1", "effective_from": "2025-01-01", "description": "Launch rates"},
+        {"id": "2024", "effective_from": "2024-01-01"},
+        {"id": "2025-q3", "effective_from": "2025-07-01"}
+    ]"#;
+
+    #[test]
+    fn test_versions_are_sorted_by_effective_date() {
+        let dir = plan_dir("plan_sorted", MANIFEST, &["2024", "2025-q1", "2025-q3"]);
+        let catalog = PlanCatalog::open(&dir).unwrap();
+        let ids: Vec<&str> = catalog.versions().iter().map(|v| v.id.as_str()).collect();
+        assert_eq!(ids, vec!["2024", "2025-q1", "2025-q3"]);
+        assert_eq!(catalog.get("2025-q1").unwrap().description, "Launch rates");
+        assert!(catalog.get("2023").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_select_version_by_quote_date() {
+        let dir = plan_dir("plan_select", MANIFEST, &["2024", "2025-q1", "2025-q3"]);
+        let catalog = PlanCatalog::open(&dir).unwrap();
+        let id = |text| catalog.effective_on(date(text)).unwrap().id.as_str();
+        assert_eq!(id("2024-12-31"), "2024");
+        assert_eq!(id("2025-01-01"), "2025-q1");
+        assert_eq!(id("2025-06-30"), "2025-q1");
+        assert_eq!(id("2030-01-01"), "2025-q3");
+
+        let err = catalog.effective_on(date("2023-06-01")).unwrap_err();
+        assert!(err.to_string().contains("2024-01-01"), "{}", err);
+
+        let repo = catalog.repository(catalog.get("2025-q3").unwrap());
+        assert_eq!(repo.version_id(), "2025-q3");
+        assert_eq!(repo.load_all().unwrap().len(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_manifests_are_rejected() {
+        let dir = plan_dir("plan_missing_dir", MANIFEST, &["2024", "2025-q1"]);
+        let err = PlanCatalog::open(&dir).unwrap_err();
+        assert!(err.to_string().contains("'2025-q3'"), "{}", err);
+        fs::remove_dir_all(dir).unwrap();
+
+        let same_day = r#"[
+            {"id": "a", "effective_from": "2025-01-01"},
+            {"id": "b", "effective_from": "2025-01-01"}
+        ]"#;
+        let dir = plan_dir("plan_same_day", same_day, &["a", "b"]);
+        assert!(PlanCatalog::open(&dir).is_err());
+        fs::remove_dir_all(dir).unwrap();
+
+        let dir = plan_dir("plan_empty", "[]", &[]);
+        assert!(PlanCatalog::open(&dir).is_err());
+        fs::remove_dir_all(dir).unwrap();
+
+        assert!(PlanCatalog::open("does/not/exist").is_err());
+    }
+}
@@ -0,0 +1,457 @@
+use crate::domain::{Applicant, CalculationFailure, PremiumResult, Rejection};
+use crate::repository::PlanCatalog;
+use crate::services::{PremiumCalculationService, ValidationRules};
+use chrono::NaiveDate;
+use log::{debug, info};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::error::Error;
+use std::net::{Ipv4Addr, SocketAddr};
+use tiny_http::{Header, Request, Server};
+
+/// Response produced by a route, before it is written to the connection
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: &impl Serialize) -> Self {
+        match serde_json::to_string(body) {
+            Ok(body) => Self { status, body },
+            Err(e) => Self::error(500, e.to_string()),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        let body = serde_json::json!({ "error": message.into() }).to_string();
+        Self { status, body }
+    }
+}
+
+#[derive(Serialize)]
+struct QuoteBatch {
+    results: Vec<PremiumResult>,
+    rejected: Vec<Rejection>,
+    failures: Vec<CalculationFailure>,
+}
+
+/// HTTP quoting service backed by a compiled rating plan
+/// Only binds to localhost; it is meant to sit behind the front-end's own backend
+pub struct QuoteServer {
+    calculator: PremiumCalculationService,
+    validation: ValidationRules,
+    /// With a plan catalog, every version compiled up front, in catalog order
+    plans: Option<(PlanCatalog, Vec<PremiumCalculationService>)>,
+}
+
+impl QuoteServer {
+    pub fn new(calculator: PremiumCalculationService) -> Self {
+        Self {
+            calculator,
+            validation: ValidationRules::default(),
+            plans: None,
+        }
+    }
+
+    /// Rules every applicant must pass before it is rated
+    pub fn with_validation_rules(mut self, rules: ValidationRules) -> Self {
+        self.validation = rules;
+        self
+    }
+
+    /// Serve every version of a catalog, picking the one in force on each request's quote date
+    /// The versions share the tables and rules of the server's calculator
+    pub fn with_plans(mut self, catalog: PlanCatalog) -> Result<Self, Box<dyn Error>> {
+        let calculators = catalog
+            .versions()
+            .iter()
+            .map(|version| {
+                self.calculator
+                    .rebuild(Box::new(catalog.repository(version)))
+                    .map_err(|e| format!("plan version '{}': {}", version.id, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.plans = Some((catalog, calculators));
+        Ok(self)
+    }
+
+    /// Bind a listener on localhost; port 0 picks a free port
+    pub fn bind(port: u16) -> Result<Server, Box<dyn Error>> {
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        Server::http(address).map_err(|e| format!("failed to bind {}: {}", address, e).into())
+    }
+
+    /// Answer requests until the listener is unblocked
+    pub fn serve(&self, http: &Server) {
+        for request in http.incoming_requests() {
+            if let Err(e) = self.respond(request) {
+                info!("failed to send response: {}", e);
+            }
+        }
+    }
+
+    fn respond(&self, mut request: Request) -> Result<(), Box<dyn Error>> {
+        let mut body = String::new();
+        let response = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => self.handle(request.method().as_str(), request.url(), &body),
+            Err(e) => Response::error(400, format!("unreadable request body: {}", e)),
+        };
+        debug!(
+            "{} {} -> {}",
+            request.method(),
+            request.url(),
+            response.status
+        );
+
+        let content_type = Header::from_bytes("Content-Type", "application/json")
+            .map_err(|_| "invalid content type header")?;
+        request.respond(
+            tiny_http::Response::from_string(response.body)
+                .with_status_code(response.status)
+                .with_header(content_type),
+        )?;
+        Ok(())
+    }
+
+    /// Route a request to its handler
+    pub fn handle(&self, method: &str, url: &str, body: &str) -> Response {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        match (method, path) {
+            ("GET", "/health") => Response::json(200, &serde_json::json!({ "status": "ok" })),
+            ("GET", "/formulas") => match self.calculator_for(query) {
+                Ok(calculator) => Response::json(
+                    200,
+                    &serde_json::json!({
+                        "plan_version": calculator.plan_version(),
+                        "formulas": calculator.formula_names(),
+                    }),
+                ),
+                Err(response) => response,
+            },
+            ("POST", "/quote") => match self.calculator_for(query) {
+                Ok(calculator) => self.quote(calculator, body),
+                Err(response) => response,
+            },
+            ("POST", "/quotes") => match self.calculator_for(query) {
+                Ok(calculator) => self.quotes(calculator, body),
+                Err(response) => response,
+            },
+            (_, "/health" | "/formulas" | "/quote" | "/quotes") => {
+                Response::error(405, format!("{} is not allowed on {}", method, path))
+            }
+            _ => Response::error(404, format!("no route for {}", path)),
+        }
+    }
+
+    /// Calculator for a request: with a plan catalog, the version in force on the
+    /// `quote_date` query parameter, today when it is absent
+    fn calculator_for(&self, query: &str) -> Result<&PremiumCalculationService, Response> {
+        let quote_date = query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("quote_date="))
+            .map(|date| {
+                date.parse::<NaiveDate>().map_err(|e| {
+                    Response::error(400, format!("invalid quote_date '{}': {}", date, e))
+                })
+            })
+            .transpose()?;
+
+        let Some((catalog, calculators)) = &self.plans else {
+            return Ok(&self.calculator);
+        };
+        let date = quote_date.unwrap_or_else(|| chrono::Local::now().date_naive());
+        let version = catalog
+            .effective_on(date)
+            .map_err(|e| Response::error(422, e.to_string()))?;
+        let index = catalog
+            .versions()
+            .iter()
+            .position(|other| other.id == version.id)
+            .unwrap_or_default();
+        Ok(&calculators[index])
+    }
+
+    fn quote(&self, calculator: &PremiumCalculationService, body: &str) -> Response {
+        let applicant: Applicant = match serde_json::from_str(body) {
+            Ok(applicant) => applicant,
+            Err(e) => return Response::error(400, format!("invalid applicant: {}", e)),
+        };
+        if let Err(rejection) = self.validation.validate(&applicant) {
+            return Response::error(422, rejection.to_string());
+        }
+
+        match Self::rate(calculator, &applicant) {
+            Ok(result) => Response::json(200, &result),
+            Err(failure) => Response::error(422, failure.to_string()),
+        }
+    }
+
+    fn quotes(&self, calculator: &PremiumCalculationService, body: &str) -> Response {
+        let applicants: Vec<Applicant> = match serde_json::from_str(body) {
+            Ok(applicants) => applicants,
+            Err(e) => return Response::error(400, format!("invalid applicants: {}", e)),
+        };
+        let report = self.validation.split(applicants);
+
+        let outcomes: Vec<Result<PremiumResult, CalculationFailure>> = report
+            .valid
+            .par_iter()
+            .map(|applicant| Self::rate(calculator, applicant))
+            .collect();
+
+        let mut batch = QuoteBatch {
+            results: Vec::with_capacity(outcomes.len()),
+            rejected: report.rejected,
+            failures: Vec::new(),
+        };
+        for outcome in outcomes {
+            match outcome {
+                Ok(result) => batch.results.push(result),
+                Err(failure) => batch.failures.push(failure),
+            }
+        }
+        Response::json(200, &batch)
+    }
+
+    fn rate(
+        calculator: &PremiumCalculationService,
+        applicant: &Applicant,
+    ) -> Result<PremiumResult, CalculationFailure> {
+        calculator
+            .calculate(applicant)
+            .map_err(|e| CalculationFailure {
+                applicant_id: applicant.id,
+                formula: e.formula,
+                message: e.message,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::InMemoryFormulaRepository;
+    use serde_json::Value;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    use std::thread;
+
+    const APPLICANT: &str = r#"{"id":7,"name":"Jane","age":45,"gender":"F","occupation":"Nurse",
+        "annual_income":65000,"bmi":24.5,"blood_pressure_sys":125,"blood_pressure_dia":82,
+        "cholesterol":200,"coverage_amount":250000,"coverage_years":20}"#;
+
+    fn server() -> QuoteServer {
+        let calculator =
+            PremiumCalculationService::new(Box::new(InMemoryFormulaRepository::new())).unwrap();
+        QuoteServer::new(calculator)
+    }
+
+    fn json(response: &Response) -> Value {
+        serde_json::from_str(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_health() {
+        let response = server().handle("GET", "/health", "");
+        assert_eq!(response.status, 200);
+        assert_eq!(json(&response)["status"], "ok");
+    }
+
+    #[test]
+    fn test_formulas() {
+        let response = server().handle("GET", "/formulas", "");
+        let formulas = json(&response)["formulas"].as_array().unwrap().clone();
+        assert_eq!(formulas.len(), 10);
+        assert!(formulas.contains(&Value::from("final_premium")));
+    }
+
+    #[test]
+    fn test_quote_returns_breakdown() {
+        let response = server().handle("POST", "/quote", APPLICANT);
+        assert_eq!(response.status, 200);
+
+        let result = json(&response);
+        assert_eq!(result["applicant"]["id"], 7);
+        assert!(result["final_premium"].as_f64().unwrap() > 0.0);
+        assert_eq!(result["outputs"]["occupation_factor"], 1.1);
+    }
+
+    #[test]
+    fn test_quote_rejects_invalid_body() {
+        let response = server().handle("POST", "/quote", "{\"age\": 45}");
+        assert_eq!(response.status, 400);
+        assert!(json(&response)["error"]
+            .as_str()
+            .unwrap()
+            .contains("gender"));
+    }
+
+    #[test]
+    fn test_quotes_keeps_request_order() {
+        let body = format!(
+            "[{}, {}]",
+            APPLICANT,
+            APPLICANT.replace("\"id\":7", "\"id\":8")
+        );
+        let response = server().handle("POST", "/quotes", &body);
+        assert_eq!(response.status, 200);
+
+        let batch = json(&response);
+        assert_eq!(batch["results"][0]["applicant"]["id"], 7);
+        assert_eq!(batch["results"][1]["applicant"]["id"], 8);
+        assert!(batch["rejected"].as_array().unwrap().is_empty());
+        assert!(batch["failures"].as_array().unwrap().is_empty());
+    }
+
+    fn plan_server() -> QuoteServer {
+        let dir = crate::test_support::temp_dir("server_plans");
+        std::fs::write(
+            dir.join("versions.json"),
+            r#"[{"id": "2024", "effective_from": "2024-01-01"},
+                {"id": "2025", "effective_from": "2025-01-01"}]"#,
+        )
+        .unwrap();
+        for (id, premium) in [("2024", 100), ("2025", 200)] {
+            std::fs::create_dir(dir.join(id)).unwrap();
+            crate::test_support::write_formulas(&dir.join(id), &format!("return {}", premium));
+        }
+        let server = server()
+            .with_plans(PlanCatalog::open(&dir).unwrap())
+            .unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        server
+    }
+
+    #[test]
+    fn test_quote_date_picks_plan_version() {
+        let server = plan_server();
+
+        let response = server.handle("POST", "/quote?quote_date=2024-06-30", APPLICANT);
+        assert_eq!(json(&response)["plan_version"], "2024");
+        assert_eq!(json(&response)["final_premium"], 100.0);
+
+        let response = server.handle("POST", "/quote?quote_date=2025-01-01", APPLICANT);
+        assert_eq!(json(&response)["plan_version"], "2025");
+        assert_eq!(json(&response)["final_premium"], 200.0);
+
+        let response = server.handle("GET", "/formulas?quote_date=2024-02-01", "");
+        assert_eq!(json(&response)["plan_version"], "2024");
+
+        // Without a date the version in force today is used
+        let response = server.handle("POST", "/quote", APPLICANT);
+        assert_eq!(json(&response)["plan_version"], "2025");
+    }
+
+    #[test]
+    fn test_plans_missing_a_rated_output_are_rejected() {
+        let dir = crate::test_support::temp_dir("server_plans_rated");
+        std::fs::write(
+            dir.join("versions.json"),
+            r#"[{"id": "2024", "effective_from": "2024-01-01"}]"#,
+        )
+        .unwrap();
+        std::fs::create_dir(dir.join("2024")).unwrap();
+        crate::test_support::write_formulas(&dir.join("2024"), "return 100");
+        std::fs::remove_file(dir.join("2024").join("lifestyle_multiplier.fc")).unwrap();
+
+        let err = server()
+            .with_plans(PlanCatalog::open(&dir).unwrap())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("plan version '2024'"), "{}", err);
+        assert!(
+            err.contains("rated_outputs names unknown formulas: lifestyle_multiplier"),
+            "{}",
+            err
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_quote_date_errors() {
+        let server = plan_server();
+
+        let response = server.handle("POST", "/quote?quote_date=June", APPLICANT);
+        assert_eq!(response.status, 400);
+        assert!(json(&response)["error"]
+            .as_str()
+            .unwrap()
+            .contains("invalid quote_date"));
+
+        let response = server.handle("POST", "/quotes?quote_date=2023-12-31", "[]");
+        assert_eq!(response.status, 422);
+        assert!(json(&response)["error"]
+            .as_str()
+            .unwrap()
+            .contains("no plan version is effective on 2023-12-31"));
+    }
+
+    #[test]
+    fn test_invalid_applicants_are_rejected_before_rating() {
+        let server = server();
+
+        let response = server.handle(
+            "POST",
+            "/quote",
+            &APPLICANT.replace("\"age\":45", "\"age\":4294967295"),
+        );
+        assert_eq!(response.status, 422);
+        assert!(json(&response)["error"]
+            .as_str()
+            .unwrap()
+            .contains("age 4294967295 is outside"));
+
+        let body = format!(
+            "[{}, {}]",
+            APPLICANT
+                .replace("\"id\":7", "\"id\":8")
+                .replace("\"age\":45", "\"age\":150"),
+            APPLICANT
+        );
+        let batch = json(&server.handle("POST", "/quotes", &body));
+        assert_eq!(batch["results"][0]["applicant"]["id"], 7);
+        assert_eq!(batch["rejected"][0]["applicant_id"], 8);
+        assert_eq!(batch["rejected"][0]["violations"][0]["rule"], "age");
+    }
+
+    #[test]
+    fn test_unknown_route_and_method() {
+        let server = server();
+        assert_eq!(server.handle("GET", "/nope", "").status, 404);
+        assert_eq!(server.handle("GET", "/quote", "").status, 405);
+    }
+
+    #[test]
+    fn test_serves_over_localhost() {
+        let http = Arc::new(QuoteServer::bind(0).unwrap());
+        let address = http.server_addr().to_ip().unwrap();
+        assert!(address.ip().is_loopback());
+
+        let listener = Arc::clone(&http);
+        let handle = thread::spawn(move || server().serve(&listener));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST /quote HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            APPLICANT.len(),
+            APPLICANT
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        http.unblock();
+        handle.join().unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"));
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        let result: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(result["applicant"]["name"], "Jane");
+    }
+}
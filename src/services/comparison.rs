@@ -0,0 +1,278 @@
+use crate::domain::PremiumResult;
+use std::collections::{HashMap, HashSet};
+
+/// Premium changes smaller than half a cent count as unchanged
+const UNCHANGED_TOLERANCE: f64 = 0.005;
+
+/// Upper bounds of the percentage-change buckets, in percent; the last bucket is open-ended
+const BUCKET_BOUNDS: [f64; 6] = [-10.0, -5.0, -1.0, 1.0, 5.0, 10.0];
+
+/// One applicant's monthly premium under the baseline and the candidate formula set
+#[derive(Debug, Clone, PartialEq)]
+pub struct PremiumChange {
+    pub applicant_id: u32,
+    pub name: String,
+    pub baseline: f64,
+    pub candidate: f64,
+}
+
+impl PremiumChange {
+    pub fn delta(&self) -> f64 {
+        self.candidate - self.baseline
+    }
+
+    /// Change relative to the baseline in percent; `None` when the baseline premium is zero
+    pub fn percent_change(&self) -> Option<f64> {
+        if self.baseline == 0.0 {
+            None
+        } else {
+            Some(self.delta() / self.baseline * 100.0)
+        }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.delta().abs() < UNCHANGED_TOLERANCE
+    }
+}
+
+/// Number of applicants whose percentage change falls in `[min, max)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeBucket {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub count: usize,
+}
+
+impl ChangeBucket {
+    /// Label such as `-5% to -1%` or `10% and above`
+    pub fn label(&self) -> String {
+        match (self.min, self.max) {
+            (None, Some(max)) => format!("below {}%", max),
+            (Some(min), None) => format!("{}% and above", min),
+            (Some(min), Some(max)) => format!("{}% to {}%", min, max),
+            (None, None) => "all".to_string(),
+        }
+    }
+}
+
+/// Impact of a rate change: premiums of the same applicants under two formula sets
+#[derive(Debug, Clone, Default)]
+pub struct PremiumComparison {
+    /// Version ids of the two formula sets, filled in by the caller
+    pub baseline_version: String,
+    pub candidate_version: String,
+    /// Applicants rated by both formula sets, in baseline order
+    pub changes: Vec<PremiumChange>,
+    /// Applicants only the baseline could rate
+    pub baseline_only: Vec<u32>,
+    /// Applicants only the candidate could rate
+    pub candidate_only: Vec<u32>,
+}
+
+impl PremiumComparison {
+    /// Pair baseline and candidate results by applicant id
+    pub fn new(baseline: &[PremiumResult], candidate: &[PremiumResult]) -> Self {
+        let candidate_premiums: HashMap<u32, f64> = candidate
+            .iter()
+            .map(|result| (result.applicant.id, result.final_premium))
+            .collect();
+        let baseline_ids: HashSet<u32> =
+            baseline.iter().map(|result| result.applicant.id).collect();
+
+        let mut comparison = Self::default();
+        for result in baseline {
+            match candidate_premiums.get(&result.applicant.id) {
+                Some(&premium) => comparison.changes.push(PremiumChange {
+                    applicant_id: result.applicant.id,
+                    name: result.applicant.name.clone(),
+                    baseline: result.final_premium,
+                    candidate: premium,
+                }),
+                None => comparison.baseline_only.push(result.applicant.id),
+            }
+        }
+        comparison.candidate_only = candidate
+            .iter()
+            .map(|result| result.applicant.id)
+            .filter(|id| !baseline_ids.contains(id))
+            .collect();
+        comparison
+    }
+
+    pub fn increased(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| !change.is_unchanged() && change.delta() > 0.0)
+            .count()
+    }
+
+    pub fn decreased(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| !change.is_unchanged() && change.delta() < 0.0)
+            .count()
+    }
+
+    pub fn unchanged(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| change.is_unchanged())
+            .count()
+    }
+
+    /// Total monthly premium under each formula set
+    pub fn totals(&self) -> (f64, f64) {
+        self.changes.iter().fold((0.0, 0.0), |(old, new), change| {
+            (old + change.baseline, new + change.candidate)
+        })
+    }
+
+    /// Percentage changes sorted ascending, leaving out zero baselines
+    fn sorted_percent_changes(&self) -> Vec<f64> {
+        let mut percents: Vec<f64> = self
+            .changes
+            .iter()
+            .filter_map(PremiumChange::percent_change)
+            .collect();
+        percents.sort_by(f64::total_cmp);
+        percents
+    }
+
+    /// Percentage change at a percentile between 0 and 100, nearest rank
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        let percents = self.sorted_percent_changes();
+        if percents.is_empty() {
+            return None;
+        }
+        let rank = (percentile / 100.0 * (percents.len() - 1) as f64).round() as usize;
+        Some(percents[rank.min(percents.len() - 1)])
+    }
+
+    pub fn mean_percent_change(&self) -> Option<f64> {
+        let percents = self.sorted_percent_changes();
+        if percents.is_empty() {
+            None
+        } else {
+            Some(percents.iter().sum::<f64>() / percents.len() as f64)
+        }
+    }
+
+    /// How many applicants fall in each band of percentage change
+    pub fn distribution(&self) -> Vec<ChangeBucket> {
+        let mut buckets: Vec<ChangeBucket> = std::iter::once(None)
+            .chain(BUCKET_BOUNDS.iter().copied().map(Some))
+            .zip(BUCKET_BOUNDS.iter().copied().map(Some).chain([None]))
+            .map(|(min, max)| ChangeBucket { min, max, count: 0 })
+            .collect();
+        for percent in self.sorted_percent_changes() {
+            // Compare at 0.01% precision so rounding noise does not move an applicant across a bound
+            let percent = (percent * 100.0).round() / 100.0;
+            let index = BUCKET_BOUNDS
+                .iter()
+                .position(|&bound| percent < bound)
+                .unwrap_or(BUCKET_BOUNDS.len());
+            buckets[index].count += 1;
+        }
+        buckets
+    }
+
+    /// The `count` applicants with the largest percentage change either way
+    pub fn biggest_movers(&self, count: usize) -> Vec<&PremiumChange> {
+        let mut movers: Vec<&PremiumChange> = self
+            .changes
+            .iter()
+            .filter(|change| !change.is_unchanged())
+            .collect();
+        movers.sort_by(|a, b| {
+            let magnitude =
+                |change: &PremiumChange| change.percent_change().map_or(f64::INFINITY, f64::abs);
+            magnitude(b).total_cmp(&magnitude(a))
+        });
+        movers.truncate(count);
+        movers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FormulaOutputs;
+
+    fn result(id: u32, final_premium: f64) -> PremiumResult {
+        let applicant = crate::test_support::applicant(id);
+        PremiumResult::new(applicant, 100.0, final_premium, FormulaOutputs::new(), 0.1)
+    }
+
+    fn comparison() -> PremiumComparison {
+        let baseline = [
+            result(1, 100.0),
+            result(2, 200.0),
+            result(3, 50.0),
+            result(4, 80.0),
+        ];
+        let candidate = [
+            result(1, 103.0),
+            result(2, 160.0),
+            result(3, 50.0),
+            result(5, 90.0),
+        ];
+        PremiumComparison::new(&baseline, &candidate)
+    }
+
+    #[test]
+    fn test_pairs_results_by_applicant_id() {
+        let comparison = comparison();
+        assert_eq!(comparison.changes.len(), 3);
+        assert_eq!(comparison.baseline_only, vec![4]);
+        assert_eq!(comparison.candidate_only, vec![5]);
+
+        assert_eq!(comparison.changes[1].delta(), -40.0);
+        assert_eq!(comparison.changes[1].percent_change(), Some(-20.0));
+        assert_eq!(
+            (
+                comparison.increased(),
+                comparison.decreased(),
+                comparison.unchanged()
+            ),
+            (1, 1, 1)
+        );
+        assert_eq!(comparison.totals(), (350.0, 313.0));
+    }
+
+    #[test]
+    fn test_distribution_and_percentiles() {
+        let comparison = comparison();
+        let counts: Vec<(String, usize)> = comparison
+            .distribution()
+            .iter()
+            .map(|bucket| (bucket.label(), bucket.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("below -10%".to_string(), 1),
+                ("-10% to -5%".to_string(), 0),
+                ("-5% to -1%".to_string(), 0),
+                ("-1% to 1%".to_string(), 1),
+                ("1% to 5%".to_string(), 1),
+                ("5% to 10%".to_string(), 0),
+                ("10% and above".to_string(), 0),
+            ]
+        );
+        assert_eq!(comparison.percentile(50.0), Some(0.0));
+        assert_eq!(comparison.percentile(0.0), Some(-20.0));
+        assert!((comparison.mean_percent_change().unwrap() - (-17.0 / 3.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_biggest_movers_ignore_unchanged() {
+        let comparison = comparison();
+        let ids: Vec<u32> = comparison
+            .biggest_movers(10)
+            .iter()
+            .map(|change| change.applicant_id)
+            .collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(comparison.biggest_movers(1)[0].applicant_id, 2);
+    }
+}
@@ -0,0 +1,353 @@
+use formcalc::{Formula, FormulaT};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+/// Formulas whose outputs make up a premium result; every other formula must feed into them
+pub const RESULT_FORMULAS: [&str; 2] = ["base_premium", "final_premium"];
+
+/// A `get_output_from('...')` call naming a formula that does not exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingReference {
+    pub formula: String,
+    pub reference: String,
+}
+
+impl fmt::Display for MissingReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "formula '{}' depends on unknown formula '{}'",
+            self.formula, self.reference
+        )
+    }
+}
+
+/// Problems found in a formula set's dependency graph
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphReport {
+    pub missing: Vec<MissingReference>,
+    /// Each cycle as the formulas on it, starting from the first by name and
+    /// ending where it started
+    pub cycles: Vec<Vec<String>>,
+    /// Formulas no result formula depends on, directly or indirectly
+    pub unused: Vec<String>,
+}
+
+impl GraphReport {
+    /// Missing references and cycles stop the formula set from running; unused formulas do not
+    pub fn has_errors(&self) -> bool {
+        !self.missing.is_empty() || !self.cycles.is_empty()
+    }
+
+    /// One line per problem, errors first
+    pub fn messages(&self) -> Vec<String> {
+        let mut messages: Vec<String> = self.missing.iter().map(ToString::to_string).collect();
+        messages.extend(
+            self.cycles
+                .iter()
+                .map(|cycle| format!("circular dependency: {}", cycle.join(" -> "))),
+        );
+        messages.extend(
+            self.unused
+                .iter()
+                .map(|name| format!("formula '{}' is not used by any result formula", name)),
+        );
+        messages
+    }
+}
+
+/// Which formulas each formula reads through `get_output_from('...')`
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Dependencies of every formula, both sorted by name
+    dependencies: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn new(formulas: &[Formula]) -> Self {
+        Self {
+            dependencies: formulas
+                .iter()
+                .map(|formula| {
+                    (
+                        formula.name().to_string(),
+                        formula.depends_on().iter().cloned().collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Formulas the given formula reads directly
+    pub fn dependencies_of(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.dependencies
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Formulas `root` only reads through another formula, each mapped to a formula reading it
+    /// Breakdowns show these as parts of that formula instead of as factors of `root`
+    pub fn sub_factors(&self, root: &str) -> BTreeMap<String, String> {
+        let direct: BTreeSet<&str> = self.dependencies_of(root).collect();
+        let mut parents = BTreeMap::new();
+        for (parent, dependencies) in &self.dependencies {
+            if parent == root {
+                continue;
+            }
+            for dependency in dependencies {
+                if dependency != root && !direct.contains(dependency.as_str()) {
+                    parents
+                        .entry(dependency.clone())
+                        .or_insert_with(|| parent.clone());
+                }
+            }
+        }
+        parents
+    }
+
+    /// Check for missing references, cycles, and formulas none of `roots` depends on
+    pub fn analyze(&self, roots: &[&str]) -> GraphReport {
+        GraphReport {
+            missing: self.missing_references(),
+            cycles: self.cycles(),
+            unused: self.unused(roots),
+        }
+    }
+
+    pub fn missing_references(&self) -> Vec<MissingReference> {
+        self.dependencies
+            .iter()
+            .flat_map(|(name, deps)| {
+                deps.iter()
+                    .filter(|dep| !self.dependencies.contains_key(*dep))
+                    .map(move |dep| MissingReference {
+                        formula: name.clone(),
+                        reference: dep.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Every elementary cycle would be exponential; one cycle per strongly connected
+    /// group of formulas is enough to point at the problem
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut cycles = Vec::new();
+        let mut reported: BTreeSet<&str> = BTreeSet::new();
+        for start in self.dependencies.keys() {
+            if reported.contains(start.as_str()) {
+                continue;
+            }
+            if let Some(cycle) = self.cycle_through(start) {
+                reported.extend(self.strongly_connected(start));
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    }
+
+    /// Shortest path from `start` back to itself, if there is one
+    fn cycle_through(&self, start: &str) -> Option<Vec<String>> {
+        let mut previous: BTreeMap<&str, &str> = BTreeMap::new();
+        let mut queue: VecDeque<&str> = self.dependencies_of(start).collect();
+        for dep in self.dependencies_of(start) {
+            previous.insert(dep, start);
+        }
+
+        while let Some(name) = queue.pop_front() {
+            if name == start {
+                let mut path = vec![start.to_string()];
+                let mut current = previous[start];
+                while current != start {
+                    path.push(current.to_string());
+                    current = previous[current];
+                }
+                path.push(start.to_string());
+                path.reverse();
+                return Some(path);
+            }
+            for dep in self.dependencies_of(name) {
+                if !previous.contains_key(dep) {
+                    previous.insert(dep, name);
+                    queue.push_back(dep);
+                }
+            }
+        }
+        None
+    }
+
+    /// Formulas that both depend on `start` and are depended on by it, `start` included
+    fn strongly_connected<'a>(&'a self, start: &'a str) -> BTreeSet<&'a str> {
+        let downstream = self.reachable(&[start], |name| self.dependencies_of(name).collect());
+        let upstream = self.reachable(&[start], |name| {
+            self.dependencies
+                .iter()
+                .filter(|(_, deps)| deps.contains(name))
+                .map(|(dependent, _)| dependent.as_str())
+                .collect()
+        });
+        downstream.intersection(&upstream).copied().collect()
+    }
+
+    /// Every formula reachable from `roots` by following `next`, the roots included
+    fn reachable<'a>(
+        &'a self,
+        roots: &[&'a str],
+        next: impl Fn(&'a str) -> Vec<&'a str>,
+    ) -> BTreeSet<&'a str> {
+        let mut seen: BTreeSet<&str> = BTreeSet::new();
+        let mut stack: Vec<&str> = roots.to_vec();
+        while let Some(name) = stack.pop() {
+            if seen.insert(name) {
+                stack.extend(next(name));
+            }
+        }
+        seen
+    }
+
+    fn unused(&self, roots: &[&str]) -> Vec<String> {
+        let used = self.reachable(roots, |name| self.dependencies_of(name).collect());
+        self.dependencies
+            .keys()
+            .filter(|name| !used.contains(name.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// The graph in Graphviz DOT format, with arrows pointing from a formula to the
+    /// formulas that read it; missing formulas are drawn dashed and cycles in red
+    pub fn to_dot(&self) -> String {
+        let report = self.analyze(&RESULT_FORMULAS);
+        let in_cycle: BTreeSet<(&str, &str)> = report
+            .cycles
+            .iter()
+            .flat_map(|cycle| cycle.windows(2))
+            .map(|pair| (pair[1].as_str(), pair[0].as_str()))
+            .collect();
+
+        let mut dot = String::from("digraph formulas {\n    rankdir=LR;\n    node [shape=box];\n");
+        for name in self.dependencies.keys() {
+            let style = if RESULT_FORMULAS.contains(&name.as_str()) {
+                " [style=bold]"
+            } else if report.unused.contains(name) {
+                " [color=gray, fontcolor=gray]"
+            } else {
+                ""
+            };
+            dot.push_str(&format!("    \"{}\"{};\n", name, style));
+        }
+        for missing in &report.missing {
+            dot.push_str(&format!(
+                "    \"{}\" [style=dashed, color=red, fontcolor=red];\n",
+                missing.reference
+            ));
+        }
+        for (name, deps) in &self.dependencies {
+            for dep in deps {
+                let style = if in_cycle.contains(&(dep.as_str(), name.as_str())) {
+                    " [color=red]"
+                } else {
+                    ""
+                };
+                dot.push_str(&format!("    \"{}\" -> \"{}\"{};\n", dep, name, style));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{FormulaRepository, InMemoryFormulaRepository};
+
+    fn graph(formulas: &[(&str, &str)]) -> DependencyGraph {
+        let formulas: Vec<Formula> = formulas
+            .iter()
+            .map(|(name, body)| Formula::new(*name, body.to_string()))
+            .collect();
+        DependencyGraph::new(&formulas)
+    }
+
+    #[test]
+    fn test_built_in_formulas_are_clean() {
+        let formulas = InMemoryFormulaRepository::new().load_all().unwrap();
+        let report = DependencyGraph::new(&formulas).analyze(&RESULT_FORMULAS);
+        assert_eq!(report, GraphReport::default());
+    }
+
+    #[test]
+    fn test_reports_missing_references_and_unused_formulas() {
+        let graph = graph(&[
+            ("base_premium", "return 100"),
+            (
+                "final_premium",
+                "return get_output_from('base_premium') * get_output_from('factor')",
+            ),
+            ("old_factor", "return 1.2"),
+        ]);
+        let report = graph.analyze(&RESULT_FORMULAS);
+        assert!(report.has_errors());
+        assert_eq!(
+            report.messages(),
+            vec![
+                "formula 'final_premium' depends on unknown formula 'factor'",
+                "formula 'old_factor' is not used by any result formula",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_each_cycle_once() {
+        let graph = graph(&[
+            ("final_premium", "return get_output_from('a')"),
+            ("a", "return get_output_from('b')"),
+            ("b", "return get_output_from('c')"),
+            ("c", "return get_output_from('a')"),
+            ("d", "return get_output_from('d')"),
+        ]);
+        let report = graph.analyze(&RESULT_FORMULAS);
+        assert_eq!(
+            report.cycles,
+            vec![vec!["a", "b", "c", "a"], vec!["d", "d"]]
+        );
+        assert_eq!(report.unused, vec!["d"]);
+    }
+
+    #[test]
+    fn test_sub_factors_of_built_in_formulas() {
+        let formulas = InMemoryFormulaRepository::new().load_all().unwrap();
+        let parents = DependencyGraph::new(&formulas).sub_factors("final_premium");
+        let parents: Vec<(&str, &str)> = parents
+            .iter()
+            .map(|(name, parent)| (name.as_str(), parent.as_str()))
+            .collect();
+        assert_eq!(
+            parents,
+            vec![
+                ("bmi_risk", "health_risk_score"),
+                ("bp_risk", "health_risk_score"),
+                ("cholesterol_risk", "health_risk_score"),
+                ("condition_loading", "lifestyle_multiplier"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dot_export() {
+        let graph = graph(&[
+            ("base_premium", "return 100"),
+            (
+                "final_premium",
+                "return get_output_from('base_premium') * get_output_from('factor')",
+            ),
+        ]);
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph formulas {"));
+        assert!(dot.contains("    \"base_premium\" -> \"final_premium\";\n"));
+        assert!(dot.contains("    \"factor\" [style=dashed, color=red, fontcolor=red];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
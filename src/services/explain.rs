@@ -0,0 +1,304 @@
+use crate::domain::FormulaTrace;
+use crate::services::formula_lint::Uses;
+use formcalc::cache::{FormulaResultCache, VariableCache};
+use formcalc::parser::{Evaluator, Expr, Program, Statement};
+use formcalc::Value;
+
+/// Record how a formula that has just been evaluated reached its output
+/// Conditions are evaluated again with the same variables and outputs to find the branch taken;
+/// only the inputs read on that path are listed
+pub(crate) fn trace(
+    name: &str,
+    program: &Program,
+    evaluator: &Evaluator,
+    variables: &VariableCache,
+    results: &FormulaResultCache,
+    output: &Value,
+) -> FormulaTrace {
+    let mut uses = Uses::default();
+    let mut branch = Vec::new();
+    follow(&program.statement, evaluator, &mut branch, &mut uses);
+
+    let inputs = uses
+        .identifiers
+        .iter()
+        .filter_map(|input| variables.get(input).map(|value| (input, value)))
+        .chain(
+            uses.outputs
+                .iter()
+                .filter_map(|input| results.get(input).map(|value| (input, value))),
+        )
+        .map(|(input, value)| (input.clone(), literal(&value)))
+        .collect();
+
+    FormulaTrace {
+        formula: name.to_string(),
+        inputs,
+        branch,
+        output: literal(output),
+    }
+}
+
+/// Walk the path the statement takes, pushing the condition of every branch taken, following
+/// nested `if`s, and noting what the evaluated conditions and the taken blocks read
+fn follow(statement: &Statement, evaluator: &Evaluator, taken: &mut Vec<String>, uses: &mut Uses) {
+    let Statement::If {
+        condition,
+        then_block,
+        else_ifs,
+        else_block,
+    } = statement
+    else {
+        uses.statement(statement);
+        return;
+    };
+
+    let arms = std::iter::once((condition, then_block.as_ref()))
+        .chain(else_ifs.iter().map(|(condition, block)| (condition, block)));
+    let mut failed = Vec::new();
+    for (condition, block) in arms {
+        uses.expr(condition);
+        if holds(condition, evaluator) {
+            taken.push(source(condition));
+            follow(block, evaluator, taken, uses);
+            return;
+        }
+        failed.push(condition);
+    }
+    if let Some(block) = else_block {
+        taken.push(none_of(&failed));
+        follow(block, evaluator, taken, uses);
+    }
+}
+
+/// The condition an `else` branch stands for: every condition before it failed
+fn none_of(failed: &[&Expr]) -> String {
+    match failed {
+        [condition] => negation(condition),
+        _ => {
+            let conditions: Vec<String> =
+                failed.iter().map(|condition| source(condition)).collect();
+            format!("none of: {}", conditions.join("; "))
+        }
+    }
+}
+
+fn holds(condition: &Expr, evaluator: &Evaluator) -> bool {
+    let program = Program {
+        statement: Statement::Return(condition.clone()),
+    };
+    matches!(evaluator.evaluate(&program), Ok(Value::Bool(true)))
+}
+
+/// A value as it would be written in a formula
+fn literal(value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("'{}'", text),
+        other => other.to_string(),
+    }
+}
+
+/// A condition that failed, as the condition that held instead
+/// Comparisons are flipped; anything else is negated with `!`
+fn negation(condition: &Expr) -> String {
+    let flipped = match condition {
+        Expr::LessThan(a, b) => Expr::GreaterThanOrEqual(a.clone(), b.clone()),
+        Expr::LessThanOrEqual(a, b) => Expr::GreaterThan(a.clone(), b.clone()),
+        Expr::GreaterThan(a, b) => Expr::LessThanOrEqual(a.clone(), b.clone()),
+        Expr::GreaterThanOrEqual(a, b) => Expr::LessThan(a.clone(), b.clone()),
+        Expr::Equal(a, b) => Expr::NotEqual(a.clone(), b.clone()),
+        Expr::NotEqual(a, b) => Expr::Equal(a.clone(), b.clone()),
+        Expr::Not(a) => return source(a),
+        other => Expr::Not(Box::new(other.clone())),
+    };
+    source(&flipped)
+}
+
+/// Binding strength of an operator, following the formcalc parser
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Or(..) => 1,
+        Expr::And(..) => 2,
+        Expr::Equal(..) | Expr::NotEqual(..) => 3,
+        Expr::LessThan(..)
+        | Expr::GreaterThan(..)
+        | Expr::LessThanOrEqual(..)
+        | Expr::GreaterThanOrEqual(..) => 4,
+        Expr::Add(..) | Expr::Subtract(..) => 5,
+        Expr::Multiply(..) | Expr::Divide(..) => 6,
+        Expr::Modulo(..) => 7,
+        Expr::Power(..) => 8,
+        Expr::Not(..) | Expr::UnaryMinus(..) => 9,
+        _ => 10,
+    }
+}
+
+/// Formula source for an expression, with only the parentheses its precedence needs
+fn source(expr: &Expr) -> String {
+    let operand = |child: &Expr, min: u8| {
+        if precedence(child) < min {
+            format!("({})", source(child))
+        } else {
+            source(child)
+        }
+    };
+    let binary = |a: &Expr, op: &str, b: &Expr| {
+        let level = precedence(expr);
+        // Operators associate to the left, so a right operand at the same level needs parentheses
+        format!("{} {} {}", operand(a, level), op, operand(b, level + 1))
+    };
+    let call = |name: &str, args: &[&Expr]| {
+        let args: Vec<String> = args.iter().map(|arg| source(arg)).collect();
+        format!("{}({})", name, args.join(", "))
+    };
+
+    match expr {
+        Expr::Number(n) => n.to_string(),
+        Expr::String(text) => format!("'{}'", text),
+        Expr::Bool(b) => b.to_string(),
+        Expr::Identifier(name) => name.clone(),
+        Expr::Add(a, b) => binary(a, "+", b),
+        Expr::Subtract(a, b) => binary(a, "-", b),
+        Expr::Multiply(a, b) => binary(a, "*", b),
+        Expr::Divide(a, b) => binary(a, "/", b),
+        Expr::Modulo(a, b) => binary(a, "mod", b),
+        Expr::Power(a, b) => format!("{} ^ {}", operand(a, 9), operand(b, 8)),
+        Expr::Equal(a, b) => binary(a, "=", b),
+        Expr::NotEqual(a, b) => binary(a, "<>", b),
+        Expr::LessThan(a, b) => binary(a, "<", b),
+        Expr::GreaterThan(a, b) => binary(a, ">", b),
+        Expr::LessThanOrEqual(a, b) => binary(a, "<=", b),
+        Expr::GreaterThanOrEqual(a, b) => binary(a, ">=", b),
+        Expr::And(a, b) => binary(a, "and", b),
+        Expr::Or(a, b) => binary(a, "or", b),
+        Expr::Not(a) => format!("!{}", operand(a, 9)),
+        Expr::UnaryMinus(a) => format!("-{}", operand(a, 9)),
+        Expr::FunctionCall { name, args } => {
+            let args: Vec<&Expr> = args.iter().collect();
+            call(name, &args)
+        }
+        Expr::Max(a, b) => call("max", &[a, b]),
+        Expr::Min(a, b) => call("min", &[a, b]),
+        Expr::Rnd(a, b) => call("rnd", &[a, b]),
+        Expr::Ceil(a) => call("ceil", &[a]),
+        Expr::Floor(a) => call("floor", &[a]),
+        Expr::Exp(a) => call("exp", &[a]),
+        Expr::Year(a) => call("year", &[a]),
+        Expr::Month(a) => call("month", &[a]),
+        Expr::Day(a) => call("day", &[a]),
+        Expr::Substr(a, b, c) => call("substr", &[a, b, c]),
+        Expr::AddDays(a, b) => call("add_days", &[a, b]),
+        Expr::GetDiffDays(a, b) => call("get_diff_days", &[a, b]),
+        Expr::PaddedString(a, b) => call("padded_string", &[a, b]),
+        Expr::GetDiffMonths(a, b) => call("get_diff_months", &[a, b]),
+        Expr::GetOutputFrom(a) => call("get_output_from", &[a]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use formcalc::cache::{FunctionCache, FunctionResultCache};
+    use formcalc::parser::Parser;
+
+    fn parse(body: &str) -> Program {
+        Parser::new(body)
+            .and_then(|mut parser| parser.parse())
+            .unwrap()
+    }
+
+    fn condition(text: &str) -> Expr {
+        match parse(&format!("return {}", text)).statement {
+            Statement::Return(expr) => expr,
+            other => panic!("unexpected statement {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_source_keeps_needed_parentheses() {
+        for text in [
+            "age < 50",
+            "blood_pressure_sys < 140 and blood_pressure_dia < 90",
+            "(a or b) and c",
+            "a - (b - c)",
+            "(a + b) * c / 2",
+            "occupation_class(occupation) = 'office'",
+            "rnd(get_output_from('base_premium') * 1.5, 2)",
+            "!(smoker and age > 60)",
+        ] {
+            assert_eq!(source(&condition(text)), text);
+        }
+        assert_eq!(source(&condition("((age)) < (50)")), "age < 50");
+    }
+
+    #[test]
+    fn test_negation_flips_comparisons() {
+        assert_eq!(negation(&condition("age < 60")), "age >= 60");
+        assert_eq!(negation(&condition("!smoker")), "smoker");
+        assert_eq!(
+            negation(&condition("a < 1 and b < 2")),
+            "!(a < 1 and b < 2)"
+        );
+    }
+
+    fn evaluator(variables: &VariableCache, results: &FormulaResultCache) -> Evaluator {
+        Evaluator::new(
+            variables.clone(),
+            results.clone(),
+            FunctionCache::new(),
+            FunctionResultCache::new(),
+        )
+    }
+
+    #[test]
+    fn test_trace_records_inputs_branch_and_output() {
+        let program = parse(
+            "if (age < 30) then return 1 else if (age < 50) then \
+             if (smoker) then return get_output_from('base') * 2 else return 1.5 end \
+             else return 2 end",
+        );
+        let variables = VariableCache::new();
+        variables.set("age".to_string(), Value::Number(45.0));
+        variables.set("smoker".to_string(), Value::Bool(false));
+        let results = FormulaResultCache::new();
+        results.set("base".to_string(), Value::Number(3.0));
+        let evaluator = evaluator(&variables, &results);
+        let output = evaluator.evaluate(&program).unwrap();
+
+        let trace = trace("f", &program, &evaluator, &variables, &results, &output);
+        // 'base' is only read in the branch that was not taken
+        assert_eq!(
+            trace.inputs,
+            vec![
+                ("age".to_string(), "45".to_string()),
+                ("smoker".to_string(), "false".to_string()),
+            ]
+        );
+        assert_eq!(trace.branch, vec!["age < 50", "!smoker"]);
+        assert_eq!(trace.output, "1.5");
+    }
+
+    #[test]
+    fn test_else_after_several_arms_names_every_failed_condition() {
+        let program = parse(
+            "if (class = 'office') then return 1.0 \
+             else if (class = 'hazardous') then return 1.5 \
+             else return 1.25 end",
+        );
+        let variables = VariableCache::new();
+        variables.set("class".to_string(), Value::String("unknown".to_string()));
+        let results = FormulaResultCache::new();
+        let evaluator = evaluator(&variables, &results);
+        let output = evaluator.evaluate(&program).unwrap();
+
+        let trace = trace("f", &program, &evaluator, &variables, &results, &output);
+        assert_eq!(
+            trace.branch,
+            vec!["none of: class = 'office'; class = 'hazardous'"]
+        );
+        assert_eq!(
+            trace.inputs,
+            vec![("class".to_string(), "'unknown'".to_string())]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use crate::domain::{CaseValue, FormulaCase};
+use crate::services::premium_calculator::PremiumCalculationService;
+use formcalc::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Largest difference accepted between a numeric output and its expected value, unless a case sets its own
+pub const DEFAULT_TOLERANCE: f64 = 1e-6;
+
+/// How one expected output of a case turned out
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaseOutcome {
+    Passed,
+    /// The formula produced a different value
+    Mismatch {
+        actual: CaseValue,
+    },
+    /// The formula could not be evaluated with the case's inputs
+    Error(String),
+}
+
+/// One formula checked against the output a case expects from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseCheck {
+    pub file: String,
+    pub case: String,
+    pub formula: String,
+    pub expected: CaseValue,
+    pub outcome: CaseOutcome,
+}
+
+impl CaseCheck {
+    pub fn passed(&self) -> bool {
+        self.outcome == CaseOutcome::Passed
+    }
+}
+
+impl fmt::Display for CaseCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: '{}' {}: ", self.file, self.case, self.formula)?;
+        match &self.outcome {
+            CaseOutcome::Passed => write!(f, "{} as expected", self.expected),
+            CaseOutcome::Mismatch { actual } => {
+                write!(f, "expected {}, got {}", self.expected, actual)?;
+                if let (CaseValue::Number(expected), CaseValue::Number(actual)) =
+                    (&self.expected, actual)
+                {
+                    // Rounded so float noise does not show up in the diff
+                    let diff = ((actual - expected) * 1e9).round() / 1e9;
+                    write!(f, " (off by {:+})", diff)?;
+                }
+                Ok(())
+            }
+            CaseOutcome::Error(message) => {
+                write!(f, "expected {}, failed: {}", self.expected, message)
+            }
+        }
+    }
+}
+
+/// Every check run from a set of cases, in case order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaseReport {
+    pub checks: Vec<CaseCheck>,
+}
+
+impl CaseReport {
+    pub fn passed(&self) -> usize {
+        self.checks.iter().filter(|check| check.passed()).count()
+    }
+
+    pub fn failures(&self) -> Vec<&CaseCheck> {
+        self.checks.iter().filter(|check| !check.passed()).collect()
+    }
+}
+
+/// Runs golden test cases against the formulas of a calculation service
+/// Each expected output is checked by evaluating that formula alone with the case's inputs
+pub struct CaseRunner<'a> {
+    calculator: &'a PremiumCalculationService,
+}
+
+impl<'a> CaseRunner<'a> {
+    pub fn new(calculator: &'a PremiumCalculationService) -> Self {
+        Self { calculator }
+    }
+
+    pub fn run(&self, cases: &[FormulaCase]) -> CaseReport {
+        let mut report = CaseReport::default();
+        for case in cases {
+            let inputs: BTreeMap<String, Value> = case
+                .inputs
+                .iter()
+                .map(|(name, value)| (name.clone(), formula_value(value)))
+                .collect();
+            let tolerance = case.tolerance.unwrap_or(DEFAULT_TOLERANCE);
+
+            for (formula, expected) in &case.expected {
+                let outcome = match self.calculator.evaluate_formula(formula, &inputs) {
+                    Ok(actual) => {
+                        let actual = case_value(actual);
+                        if agrees(expected, &actual, tolerance) {
+                            CaseOutcome::Passed
+                        } else {
+                            CaseOutcome::Mismatch { actual }
+                        }
+                    }
+                    Err(e) => CaseOutcome::Error(e.to_string()),
+                };
+                report.checks.push(CaseCheck {
+                    file: case.file.clone(),
+                    case: case.name.clone(),
+                    formula: formula.clone(),
+                    expected: expected.clone(),
+                    outcome,
+                });
+            }
+        }
+        report
+    }
+}
+
+fn agrees(expected: &CaseValue, actual: &CaseValue, tolerance: f64) -> bool {
+    match (expected, actual) {
+        (CaseValue::Number(expected), CaseValue::Number(actual)) => {
+            (expected - actual).abs() <= tolerance
+        }
+        _ => expected == actual,
+    }
+}
+
+fn formula_value(value: &CaseValue) -> Value {
+    match value {
+        CaseValue::Bool(value) => Value::Bool(*value),
+        CaseValue::Number(value) => Value::Number(*value),
+        CaseValue::Text(value) => Value::String(value.clone()),
+    }
+}
+
+fn case_value(value: Value) -> CaseValue {
+    match value {
+        Value::Bool(value) => CaseValue::Bool(value),
+        Value::Number(value) => CaseValue::Number(value),
+        Value::String(value) => CaseValue::Text(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{load_formula_cases, InMemoryFormulaRepository};
+    use std::path::Path;
+
+    fn service() -> PremiumCalculationService {
+        PremiumCalculationService::new(Box::new(InMemoryFormulaRepository::new())).unwrap()
+    }
+
+    fn case(
+        name: &str,
+        inputs: &[(&str, CaseValue)],
+        expected: &[(&str, CaseValue)],
+    ) -> FormulaCase {
+        let map = |pairs: &[(&str, CaseValue)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect()
+        };
+        FormulaCase {
+            file: "cases.json".to_string(),
+            name: name.to_string(),
+            inputs: map(inputs),
+            expected: map(expected),
+            tolerance: None,
+        }
+    }
+
+    #[test]
+    fn test_reports_mismatches_with_diff() {
+        let service = service();
+        let report = CaseRunner::new(&service).run(&[
+            case(
+                "moderate",
+                &[("family_history_score", CaseValue::Number(4.0))],
+                &[("family_history_factor", CaseValue::Number(1.15))],
+            ),
+            case(
+                "high",
+                &[("family_history_score", CaseValue::Number(5.0))],
+                &[("family_history_factor", CaseValue::Number(1.2))],
+            ),
+        ]);
+        assert_eq!(report.passed(), 1);
+        let failures = report.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(
+            failures[0].to_string(),
+            "cases.json: 'high' family_history_factor: expected 1.2, got 1.3 (off by +0.1)"
+        );
+    }
+
+    #[test]
+    fn test_tolerance_and_errors() {
+        let service = service();
+        let mut close = case(
+            "rounded",
+            &[
+                ("bmi_risk", CaseValue::Number(1.3)),
+                ("bp_risk", CaseValue::Number(1.2)),
+                ("cholesterol_risk", CaseValue::Number(1.15)),
+            ],
+            &[("health_risk_score", CaseValue::Number(1.79))],
+        );
+        close.tolerance = Some(0.005);
+        let missing_input = case(
+            "no score",
+            &[],
+            &[("family_history_factor", CaseValue::Number(1.0))],
+        );
+
+        let report = CaseRunner::new(&service).run(&[close, missing_input]);
+        assert!(report.checks[0].passed(), "{}", report.checks[0]);
+        assert!(matches!(report.checks[1].outcome, CaseOutcome::Error(_)));
+    }
+
+    #[test]
+    fn test_shipped_cases_pass() {
+        let service = service();
+        let cases =
+            load_formula_cases(&Path::new(env!("CARGO_MANIFEST_DIR")).join("formulas/cases"))
+                .unwrap();
+        let report = CaseRunner::new(&service).run(&cases);
+        let failures: Vec<String> = report.failures().iter().map(ToString::to_string).collect();
+        assert!(failures.is_empty(), "{:#?}", failures);
+        assert!(report.passed() > 0);
+    }
+}
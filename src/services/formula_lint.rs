@@ -0,0 +1,342 @@
+use formcalc::parser::{Expr, Parser, Statement};
+use formcalc::{Formula, FormulaT};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+/// Largest edit distance at which an unknown name is reported with a suggestion
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// A name a formula uses that the engine will not be able to resolve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownName {
+    pub formula: String,
+    pub name: String,
+    pub kind: NameKind,
+    /// What the author most likely meant
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    Variable,
+    /// A function call, with the number of arguments it was given
+    Function(usize),
+}
+
+impl fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            NameKind::Variable => write!(
+                f,
+                "formula '{}' reads unknown variable '{}'",
+                self.formula, self.name
+            )?,
+            NameKind::Function(args) => write!(
+                f,
+                "formula '{}' calls unknown function '{}' with {} arguments",
+                self.formula, self.name, args
+            )?,
+        }
+        match &self.hint {
+            Some(hint) => write!(f, "; {}", hint),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Outcome of checking the names used by a formula set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintReport {
+    /// Unknown variables and functions, in formula order
+    pub unknown: Vec<UnknownName>,
+    /// Provided variables no formula reads, sorted by name
+    pub unused_variables: Vec<String>,
+}
+
+impl LintReport {
+    /// Unknown names fail evaluation whenever the branch using them runs; unused variables do not
+    pub fn has_errors(&self) -> bool {
+        !self.unknown.is_empty()
+    }
+
+    pub fn errors(&self) -> Vec<String> {
+        self.unknown.iter().map(ToString::to_string).collect()
+    }
+}
+
+/// Checks every identifier and function call in a formula set against the variables and
+/// lookup functions the engine provides
+pub struct FormulaLint {
+    variables: BTreeSet<String>,
+    functions: Vec<(String, usize)>,
+}
+
+impl FormulaLint {
+    pub fn new<'a>(
+        variables: impl IntoIterator<Item = &'a str>,
+        functions: impl IntoIterator<Item = (String, usize)>,
+    ) -> Self {
+        Self {
+            variables: variables.into_iter().map(str::to_string).collect(),
+            functions: functions.into_iter().collect(),
+        }
+    }
+
+    pub fn check(&self, formulas: &[Formula]) -> Result<LintReport, Box<dyn Error>> {
+        let formula_names: BTreeSet<&str> = formulas.iter().map(|f| f.name()).collect();
+        let mut report = LintReport::default();
+        let mut read: BTreeSet<String> = BTreeSet::new();
+
+        for formula in formulas {
+            let program = Parser::new(formula.body())
+                .and_then(|mut parser| parser.parse())
+                .map_err(|e| format!("formula '{}': {}", formula.name(), e))?;
+            let mut uses = Uses::default();
+            uses.statement(&program.statement);
+
+            for name in uses.identifiers {
+                if self.variables.contains(&name) {
+                    read.insert(name);
+                } else {
+                    let hint = if formula_names.contains(name.as_str()) {
+                        Some(format!(
+                            "'{}' is a formula, read it with get_output_from('{}')",
+                            name, name
+                        ))
+                    } else {
+                        closest(&name, self.variables.iter().map(String::as_str))
+                            .map(|suggestion| format!("did you mean '{}'?", suggestion))
+                    };
+                    report.unknown.push(UnknownName {
+                        formula: formula.name().to_string(),
+                        name,
+                        kind: NameKind::Variable,
+                        hint,
+                    });
+                }
+            }
+
+            for (name, args) in uses.calls {
+                if self.functions.iter().any(|(f, n)| *f == name && *n == args) {
+                    continue;
+                }
+                let hint = match self.functions.iter().find(|(f, _)| *f == name) {
+                    Some((_, expected)) => Some(format!("it takes {} arguments", expected)),
+                    None => closest(&name, self.functions.iter().map(|(f, _)| f.as_str()))
+                        .map(|suggestion| format!("did you mean '{}'?", suggestion)),
+                };
+                report.unknown.push(UnknownName {
+                    formula: formula.name().to_string(),
+                    name,
+                    kind: NameKind::Function(args),
+                    hint,
+                });
+            }
+        }
+
+        report.unused_variables = self.variables.difference(&read).cloned().collect();
+        Ok(report)
+    }
+}
+
+/// Distinct identifiers, custom function calls and `get_output_from` references found in a
+/// formula, in source order
+#[derive(Default)]
+pub(crate) struct Uses {
+    pub identifiers: Vec<String>,
+    pub calls: Vec<(String, usize)>,
+    pub outputs: Vec<String>,
+}
+
+impl Uses {
+    pub fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Return(expr) | Statement::Error(expr) => self.expr(expr),
+            Statement::If {
+                condition,
+                then_block,
+                else_ifs,
+                else_block,
+            } => {
+                self.expr(condition);
+                self.statement(then_block);
+                for (condition, block) in else_ifs {
+                    self.expr(condition);
+                    self.statement(block);
+                }
+                if let Some(block) = else_block {
+                    self.statement(block);
+                }
+            }
+        }
+    }
+
+    pub fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(_) | Expr::String(_) | Expr::Bool(_) => {}
+            Expr::Identifier(name) => {
+                if !self.identifiers.contains(name) {
+                    self.identifiers.push(name.clone());
+                }
+            }
+            Expr::FunctionCall { name, args } => {
+                let call = (name.clone(), args.len());
+                if !self.calls.contains(&call) {
+                    self.calls.push(call);
+                }
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            Expr::Add(a, b)
+            | Expr::Subtract(a, b)
+            | Expr::Multiply(a, b)
+            | Expr::Divide(a, b)
+            | Expr::Power(a, b)
+            | Expr::Modulo(a, b)
+            | Expr::Equal(a, b)
+            | Expr::NotEqual(a, b)
+            | Expr::LessThan(a, b)
+            | Expr::GreaterThan(a, b)
+            | Expr::LessThanOrEqual(a, b)
+            | Expr::GreaterThanOrEqual(a, b)
+            | Expr::And(a, b)
+            | Expr::Or(a, b)
+            | Expr::Max(a, b)
+            | Expr::Min(a, b)
+            | Expr::Rnd(a, b)
+            | Expr::AddDays(a, b)
+            | Expr::GetDiffDays(a, b)
+            | Expr::PaddedString(a, b)
+            | Expr::GetDiffMonths(a, b) => {
+                self.expr(a);
+                self.expr(b);
+            }
+            Expr::Not(a)
+            | Expr::UnaryMinus(a)
+            | Expr::Ceil(a)
+            | Expr::Floor(a)
+            | Expr::Exp(a)
+            | Expr::Year(a)
+            | Expr::Month(a)
+            | Expr::Day(a) => self.expr(a),
+            Expr::GetOutputFrom(a) => {
+                if let Expr::String(name) = a.as_ref() {
+                    if !self.outputs.contains(name) {
+                        self.outputs.push(name.clone());
+                    }
+                }
+                self.expr(a);
+            }
+            Expr::Substr(a, b, c) => {
+                self.expr(a);
+                self.expr(b);
+                self.expr(c);
+            }
+        }
+    }
+}
+
+/// The candidate closest to `name` by edit distance, if it is close enough
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two names
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint() -> FormulaLint {
+        FormulaLint::new(
+            ["age", "cholesterol", "smoker"],
+            [("occupation_class".to_string(), 1)],
+        )
+    }
+
+    fn formula(name: &str, body: &str) -> Formula {
+        Formula::new(name, body.to_string())
+    }
+
+    #[test]
+    fn test_clean_formulas_pass() {
+        let report = lint()
+            .check(&[
+                formula(
+                    "a",
+                    "if (age < 30 and smoker) then return 1 else return 2 end",
+                ),
+                formula(
+                    "b",
+                    "return get_output_from('a') * occupation_class(cholesterol)",
+                ),
+            ])
+            .unwrap();
+        assert!(!report.has_errors());
+        assert!(report.unused_variables.is_empty());
+    }
+
+    #[test]
+    fn test_reports_unknown_names_with_hints() {
+        let report = lint()
+            .check(&[
+                formula(
+                    "risk",
+                    "if (cholestrol > 240) then return 1.5 else return 1 end",
+                ),
+                formula(
+                    "total",
+                    "return risk * occupation_class(age, 2) + rate(age)",
+                ),
+            ])
+            .unwrap();
+        assert_eq!(
+            report.errors(),
+            vec![
+                "formula 'risk' reads unknown variable 'cholestrol'; did you mean 'cholesterol'?",
+                "formula 'total' reads unknown variable 'risk'; 'risk' is a formula, read it with get_output_from('risk')",
+                "formula 'total' calls unknown function 'occupation_class' with 2 arguments; it takes 1 arguments",
+                "formula 'total' calls unknown function 'rate' with 1 arguments",
+            ]
+        );
+        // The misspelt variable counts as unused too
+        assert_eq!(report.unused_variables, vec!["cholesterol", "smoker"]);
+    }
+
+    #[test]
+    fn test_unknown_names_in_untaken_branches_are_found() {
+        let report = lint()
+            .check(&[formula(
+                "a",
+                "if (age < 30) then return 1 else if (age < 60) then return bmi else error(reason) end",
+            )])
+            .unwrap();
+        let names: Vec<&str> = report.unknown.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["bmi", "reason"]);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("cholestrol", "cholesterol"), 1);
+        assert_eq!(edit_distance("bmi", "bmi"), 0);
+        assert_eq!(edit_distance("", "age"), 3);
+    }
+}
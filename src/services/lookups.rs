@@ -0,0 +1,133 @@
+use crate::domain::{Gender, SmokerStatus};
+use crate::repository::{BaseRateTable, OccupationRiskTable};
+use formcalc::{CalculatorError, Function, Result, Value};
+use std::sync::Arc;
+
+/// Class returned for occupations missing from the risk table
+pub const UNKNOWN_OCCUPATION_CLASS: &str = "unknown";
+
+/// Formula function `occupation_class(occupation)` backed by the occupation risk table
+/// Returns the class name, or `unknown` for unlisted occupations
+pub struct OccupationClassFunction {
+    table: Arc<OccupationRiskTable>,
+}
+
+impl OccupationClassFunction {
+    pub fn new(table: Arc<OccupationRiskTable>) -> Self {
+        Self { table }
+    }
+}
+
+impl Function for OccupationClassFunction {
+    fn name(&self) -> &str {
+        "occupation_class"
+    }
+
+    fn num_args(&self) -> usize {
+        1
+    }
+
+    fn execute(&self, params: &[Value]) -> Result<Value> {
+        match &params[0] {
+            Value::String(occupation) => Ok(Value::String(
+                self.table
+                    .class_of(occupation)
+                    .map(|class| class.as_str())
+                    .unwrap_or(UNKNOWN_OCCUPATION_CLASS)
+                    .to_string(),
+            )),
+            _ => Err(CalculatorError::TypeError(
+                "occupation_class requires a string".to_string(),
+            )),
+        }
+    }
+}
+
+/// Formula function `base_rate(age, gender, smoker, coverage_years)` backed by the base rate table
+/// Fails for applicants outside every band, so they are reported instead of mispriced
+pub struct BaseRateFunction {
+    table: Arc<BaseRateTable>,
+}
+
+impl BaseRateFunction {
+    pub fn new(table: Arc<BaseRateTable>) -> Self {
+        Self { table }
+    }
+}
+
+impl Function for BaseRateFunction {
+    fn name(&self) -> &str {
+        "base_rate"
+    }
+
+    fn num_args(&self) -> usize {
+        4
+    }
+
+    fn execute(&self, params: &[Value]) -> Result<Value> {
+        let (age, gender, smoker, term) = match params {
+            [Value::Number(age), Value::String(gender), Value::Bool(smoker), Value::Number(term)] => {
+                (*age, gender, *smoker, *term)
+            }
+            _ => {
+                return Err(CalculatorError::TypeError(
+                    "base_rate requires age, gender, smoker and coverage_years".to_string(),
+                ))
+            }
+        };
+        let gender: Gender = gender.parse().map_err(CalculatorError::InvalidArgument)?;
+        let smoker = SmokerStatus::from(smoker);
+
+        self.table
+            .rate(age as u32, gender, smoker, term as u32)
+            .map(Value::Number)
+            .ok_or_else(|| {
+                CalculatorError::EvalError(format!(
+                    "no base rate for age {}, gender {}, {}, term {} years",
+                    age,
+                    gender,
+                    if smoker.is_smoker() {
+                        "smoker"
+                    } else {
+                        "non-smoker"
+                    },
+                    term
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_occupation_class_lookup() {
+        let function = OccupationClassFunction::new(Arc::new(OccupationRiskTable::default()));
+        assert_eq!(
+            function.execute(&[Value::from("Pilot")]).unwrap(),
+            Value::from("hazardous")
+        );
+        assert_eq!(
+            function.execute(&[Value::from("Astronaut")]).unwrap(),
+            Value::from(UNKNOWN_OCCUPATION_CLASS)
+        );
+    }
+
+    #[test]
+    fn test_base_rate_lookup() {
+        let function = BaseRateFunction::new(Arc::new(BaseRateTable::default()));
+        let rate = |age: f64, term: f64| {
+            function.execute(&[
+                Value::Number(age),
+                Value::from("M"),
+                Value::Bool(false),
+                Value::Number(term),
+            ])
+        };
+        assert_eq!(rate(35.0, 20.0).unwrap(), Value::Number(0.5));
+
+        let err = rate(35.0, 60.0).unwrap_err().to_string();
+        assert!(err.contains("no base rate for age 35"), "{}", err);
+    }
+}
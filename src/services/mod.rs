@@ -1,3 +1,20 @@
+mod comparison;
+mod dependency_graph;
+mod explain;
+mod formula_cases;
+mod formula_lint;
+mod lookups;
+mod modal_factors;
 mod premium_calculator;
+mod rating_plan;
+mod underwriting;
+mod validation;
 
+pub use comparison::{ChangeBucket, PremiumChange, PremiumComparison};
+pub use dependency_graph::{DependencyGraph, GraphReport, MissingReference, RESULT_FORMULAS};
+pub use formula_cases::{CaseCheck, CaseOutcome, CaseReport, CaseRunner, DEFAULT_TOLERANCE};
+pub use formula_lint::{FormulaLint, LintReport, NameKind, UnknownName};
+pub use modal_factors::ModalFactors;
 pub use premium_calculator::PremiumCalculationService;
+pub use underwriting::{Thresholds, UnderwritingRules};
+pub use validation::{counts_by_rule, Range, ValidationReport, ValidationRules};
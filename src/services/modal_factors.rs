@@ -0,0 +1,108 @@
+use crate::domain::{ModalPremium, PaymentMode};
+use serde::Deserialize;
+use std::error::Error;
+
+/// Built-in modal factors, shipped as `data/modal_factors.json`
+const DEFAULT_MODAL_FACTORS: &str = include_str!("../../data/modal_factors.json");
+
+/// Multipliers applied to the annual premium for each payment mode
+/// Below 1.0 is a discount for paying less often, above 1.0 a loading for paying more often
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModalFactors {
+    pub monthly: f64,
+    pub quarterly: f64,
+    pub semi_annual: f64,
+    pub annual: f64,
+}
+
+impl ModalFactors {
+    /// Load factors from a JSON file with the same shape as `data/modal_factors.json`
+    pub fn from_json(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let json = std::fs::read_to_string(file_path)
+            .map_err(|e| format!("failed to open modal factors {}: {}", file_path, e))?;
+        Self::parse(&json).map_err(|e| format!("{}: {}", file_path, e).into())
+    }
+
+    fn parse(json: &str) -> Result<Self, Box<dyn Error>> {
+        let factors: Self = serde_json::from_str(json)?;
+        for mode in PaymentMode::ALL {
+            let factor = factors.factor(mode);
+            if factor <= 0.0 {
+                return Err(format!("{} factor {} is not positive", mode.as_str(), factor).into());
+            }
+        }
+        Ok(factors)
+    }
+
+    pub fn factor(&self, mode: PaymentMode) -> f64 {
+        match mode {
+            PaymentMode::Monthly => self.monthly,
+            PaymentMode::Quarterly => self.quarterly,
+            PaymentMode::SemiAnnual => self.semi_annual,
+            PaymentMode::Annual => self.annual,
+        }
+    }
+
+    /// What each payment mode costs for an annual premium
+    pub fn payment_options(&self, annual_premium: f64) -> Vec<ModalPremium> {
+        PaymentMode::ALL
+            .iter()
+            .map(|&mode| {
+                let factor = self.factor(mode);
+                let installments = mode.installments_per_year() as f64;
+                let installment = (annual_premium * factor / installments * 100.0).round() / 100.0;
+                ModalPremium {
+                    mode,
+                    factor,
+                    installment,
+                    annual_total: installment * installments,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for ModalFactors {
+    fn default() -> Self {
+        Self::parse(DEFAULT_MODAL_FACTORS).expect("built-in modal factors are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payment_options() {
+        let options = ModalFactors::default().payment_options(1200.0);
+        let modes: Vec<PaymentMode> = options.iter().map(|option| option.mode).collect();
+        assert_eq!(modes, PaymentMode::ALL);
+
+        // Monthly carries a 2% loading, annual a 3% discount
+        assert_eq!(options[0].installment, 102.0);
+        assert_eq!(options[0].annual_total, 1224.0);
+        assert_eq!(options[1].installment, 303.0);
+        assert_eq!(options[2].installment, 600.0);
+        assert_eq!(options[3].installment, 1164.0);
+    }
+
+    #[test]
+    fn test_installments_are_rounded_to_cents() {
+        let monthly = ModalFactors::default().payment_options(1000.0)[0];
+        assert_eq!(monthly.installment, 85.0);
+
+        let monthly = ModalFactors::default().payment_options(1234.56)[0];
+        assert_eq!(monthly.installment, 104.94);
+        assert!((monthly.annual_total - 1259.28).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_invalid_factors_are_rejected() {
+        let json = DEFAULT_MODAL_FACTORS.replace("0.97", "0");
+        assert!(ModalFactors::parse(&json).is_err());
+
+        let json = DEFAULT_MODAL_FACTORS.replace("\"annual\"", "\"yearly\"");
+        assert!(ModalFactors::parse(&json).is_err());
+    }
+}
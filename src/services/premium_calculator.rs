@@ -1,144 +1,444 @@
-use crate::domain::{Applicant, PremiumResult};
-use crate::repository::FormulaRepository;
-use formcalc::{Engine, Value};
+use crate::domain::{Applicant, Condition, FormulaOutputs, PremiumResult};
+use crate::repository::{BaseRateTable, FormulaRepository, OccupationRiskTable};
+use crate::services::dependency_graph::{DependencyGraph, RESULT_FORMULAS};
+use crate::services::formula_lint::{FormulaLint, LintReport};
+use crate::services::lookups::{BaseRateFunction, OccupationClassFunction};
+use crate::services::modal_factors::ModalFactors;
+use crate::services::rating_plan::{FormulaError, RatingPlan};
+use crate::services::underwriting::UnderwritingRules;
+use formcalc::cache::{FormulaResultCache, VariableCache};
+use formcalc::{Formula, Function, Value};
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Instant;
 
+/// Reads one engine variable from an applicant
+type VariableReader = fn(&Applicant) -> Value;
+
+/// Variables set for every applicant, and how each is read
+const VARIABLES: [(&str, VariableReader); 19] = [
+    ("age", |a| Value::Number(a.age as f64)),
+    ("gender", |a| Value::String(a.gender.as_str().to_string())),
+    ("smoker", |a| Value::Bool(a.smoker.is_smoker())),
+    ("occupation", |a| Value::String(a.occupation.clone())),
+    ("bmi", |a| Value::Number(a.bmi)),
+    ("blood_pressure_sys", |a| {
+        Value::Number(a.blood_pressure_sys as f64)
+    }),
+    ("blood_pressure_dia", |a| {
+        Value::Number(a.blood_pressure_dia as f64)
+    }),
+    ("cholesterol", |a| Value::Number(a.cholesterol as f64)),
+    ("family_history_score", |a| {
+        Value::Number(a.family_history_score as f64)
+    }),
+    ("coverage_amount", |a| Value::Number(a.coverage_amount)),
+    ("coverage_years", |a| Value::Number(a.coverage_years as f64)),
+    ("annual_income", |a| Value::Number(a.annual_income)),
+    ("has_conditions", |a| {
+        Value::Bool(a.has_existing_conditions())
+    }),
+    ("condition_count", |a| {
+        Value::Number(a.existing_conditions.len() as f64)
+    }),
+    // One 0/1 flag per rated condition, named after it
+    ("diabetes", |a| flag(a.has_condition(Condition::Diabetes))),
+    ("hypertension", |a| {
+        flag(a.has_condition(Condition::Hypertension))
+    }),
+    ("asthma", |a| flag(a.has_condition(Condition::Asthma))),
+    ("arthritis", |a| flag(a.has_condition(Condition::Arthritis))),
+    ("other_conditions", |a| {
+        flag(a.has_condition(Condition::Other))
+    }),
+];
+
+/// A 0/1 number the formulas can multiply by
+fn flag(set: bool) -> Value {
+    Value::Number(if set { 1.0 } else { 0.0 })
+}
+
+/// A formula set read once, compiled and checked
+struct LoadedFormulas {
+    plan: RatingPlan,
+    lint: LintReport,
+    unused_formulas: Vec<String>,
+    sub_factors: BTreeMap<String, String>,
+}
+
 /// Service responsible for calculating insurance premiums
 /// Follows Single Responsibility Principle - only handles premium calculations
 pub struct PremiumCalculationService {
-    formula_repository: Box<dyn FormulaRepository>,
+    formula_repository: Arc<dyn FormulaRepository>,
+    occupations: Arc<OccupationRiskTable>,
+    base_rates: Arc<BaseRateTable>,
+    underwriting: UnderwritingRules,
+    modal_factors: ModalFactors,
+    plan: RatingPlan,
+    lint: LintReport,
+    unused_formulas: Vec<String>,
+    sub_factors: BTreeMap<String, String>,
+    explain: bool,
 }
 
 impl PremiumCalculationService {
-    pub fn new(formula_repository: Box<dyn FormulaRepository>) -> Self {
-        Self { formula_repository }
+    /// Create the service, loading and compiling the formula set once
+    /// Uses the built-in occupation risk and base rate tables, underwriting rules and modal factors
+    pub fn new(formula_repository: Box<dyn FormulaRepository>) -> Result<Self, Box<dyn Error>> {
+        let occupations = Arc::new(OccupationRiskTable::default());
+        let base_rates = Arc::new(BaseRateTable::default());
+        let loaded = Self::load(formula_repository.as_ref(), &occupations, &base_rates)?;
+        Ok(Self {
+            formula_repository: Arc::from(formula_repository),
+            occupations,
+            base_rates,
+            underwriting: UnderwritingRules::default(),
+            modal_factors: ModalFactors::default(),
+            plan: loaded.plan,
+            lint: loaded.lint,
+            unused_formulas: loaded.unused_formulas,
+            sub_factors: loaded.sub_factors,
+            explain: false,
+        })
     }
 
-    /// Calculate premium for a single applicant
-    pub fn calculate(&self, applicant: &Applicant) -> Result<PremiumResult, Box<dyn Error>> {
-        let start = Instant::now();
+    /// Replace the occupation risk table used by the `occupation_class` lookup
+    pub fn with_occupation_table(mut self, table: OccupationRiskTable) -> Self {
+        self.occupations = Arc::new(table);
+        self.plan
+            .register_function(Arc::new(OccupationClassFunction::new(
+                self.occupations.clone(),
+            )));
+        self
+    }
 
-        let mut engine = Engine::new();
-        self.set_variables(&mut engine, applicant);
+    /// Replace the base rate table used by the `base_rate` lookup
+    pub fn with_base_rate_table(mut self, table: BaseRateTable) -> Self {
+        self.base_rates = Arc::new(table);
+        self.plan
+            .register_function(Arc::new(BaseRateFunction::new(self.base_rates.clone())));
+        self
+    }
 
-        let formulas = self.formula_repository.load_all()?;
-        engine.execute(formulas)?;
+    /// Decide on applicants with the given rules instead of the built-in ones
+    /// Fails when a rated output is not a formula of this rating plan
+    pub fn with_underwriting_rules(
+        mut self,
+        rules: UnderwritingRules,
+    ) -> Result<Self, Box<dyn Error>> {
+        rules
+            .check_rated_outputs(&self.formula_names())
+            .map_err(|e| format!("{} in plan version {}", e, self.plan_version()))?;
+        self.underwriting = rules;
+        Ok(self)
+    }
 
-        let result = self.extract_results(&engine, applicant)?;
-        let calculation_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    /// Bill payment modes with the given factors instead of the built-in ones
+    pub fn with_modal_factors(mut self, factors: ModalFactors) -> Self {
+        self.modal_factors = factors;
+        self
+    }
 
-        Ok(PremiumResult::new(
-            applicant.clone(),
-            result.base_premium,
-            result.age_factor,
-            result.health_risk_score,
-            result.lifestyle_multiplier,
-            result.occupation_factor,
-            result.final_premium,
-            calculation_time_ms,
-        ))
+    /// In explain mode every result records how each formula reached its output
+    /// Tracing re-evaluates branch conditions, so it slows rating down
+    pub fn with_explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
     }
 
-    /// Set applicant data as variables in the formula engine
-    fn set_variables(&self, engine: &mut Engine, applicant: &Applicant) {
-        engine.set_variable("age".to_string(), Value::Number(applicant.age as f64));
-        engine.set_variable(
-            "gender".to_string(),
-            Value::String(applicant.gender.clone()),
-        );
-        engine.set_variable("smoker".to_string(), Value::Bool(applicant.smoker));
-        engine.set_variable("bmi".to_string(), Value::Number(applicant.bmi));
-        engine.set_variable(
-            "blood_pressure_sys".to_string(),
-            Value::Number(applicant.blood_pressure_sys as f64),
-        );
-        engine.set_variable(
-            "blood_pressure_dia".to_string(),
-            Value::Number(applicant.blood_pressure_dia as f64),
-        );
-        engine.set_variable(
-            "cholesterol".to_string(),
-            Value::Number(applicant.cholesterol as f64),
-        );
-        engine.set_variable(
-            "family_history_score".to_string(),
-            Value::Number(applicant.family_history_score as f64),
-        );
-        engine.set_variable(
-            "coverage_amount".to_string(),
-            Value::Number(applicant.coverage_amount),
-        );
-        engine.set_variable(
-            "coverage_years".to_string(),
-            Value::Number(applicant.coverage_years as f64),
-        );
-        engine.set_variable(
-            "annual_income".to_string(),
-            Value::Number(applicant.annual_income),
-        );
-        engine.set_variable(
-            "has_conditions".to_string(),
-            Value::Bool(applicant.has_existing_conditions()),
-        );
+    /// Read the formula set once, then check its dependency graph and names and compile it
+    /// Every check sees the same source, even when the scripts change while loading
+    fn load(
+        formula_repository: &dyn FormulaRepository,
+        occupations: &Arc<OccupationRiskTable>,
+        base_rates: &Arc<BaseRateTable>,
+    ) -> Result<LoadedFormulas, Box<dyn Error>> {
+        let formulas = formula_repository.load_all()?;
+        let graph = DependencyGraph::new(&formulas);
+        let unused_formulas = graph.analyze(&RESULT_FORMULAS).unused;
+        let sub_factors = graph.sub_factors("final_premium");
+        let lint = Self::lint(
+            &formulas,
+            formula_repository.version_id(),
+            occupations,
+            base_rates,
+        )?;
+        let plan = Self::compile(formulas, occupations, base_rates)?;
+        Ok(LoadedFormulas {
+            plan,
+            lint,
+            unused_formulas,
+            sub_factors,
+        })
+    }
+
+    /// Compile the formula set, registering the lookup functions it may call
+    fn compile(
+        formulas: Vec<Formula>,
+        occupations: &Arc<OccupationRiskTable>,
+        base_rates: &Arc<BaseRateTable>,
+    ) -> Result<RatingPlan, Box<dyn Error>> {
+        let mut plan = RatingPlan::compile(formulas)?;
+        for function in Self::functions(occupations, base_rates) {
+            plan.register_function(function);
+        }
+        Ok(plan)
+    }
+
+    /// Lookup functions the formulas may call
+    fn functions(
+        occupations: &Arc<OccupationRiskTable>,
+        base_rates: &Arc<BaseRateTable>,
+    ) -> Vec<Arc<dyn Function>> {
+        vec![
+            Arc::new(OccupationClassFunction::new(occupations.clone())),
+            Arc::new(BaseRateFunction::new(base_rates.clone())),
+        ]
     }
 
-    /// Extract calculation results from the engine
-    fn extract_results(
+    /// Check every variable and function the formulas use against what the service provides
+    /// A name the engine cannot resolve fails the load, even in a branch few applicants reach
+    fn lint(
+        formulas: &[Formula],
+        version_id: &str,
+        occupations: &Arc<OccupationRiskTable>,
+        base_rates: &Arc<BaseRateTable>,
+    ) -> Result<LintReport, Box<dyn Error>> {
+        let functions = Self::functions(occupations, base_rates)
+            .iter()
+            .map(|function| (function.name().to_string(), function.num_args()))
+            .collect::<Vec<_>>();
+        let report = FormulaLint::new(Self::variable_names(), functions).check(formulas)?;
+        if report.has_errors() {
+            return Err(format!(
+                "formula set {} uses unknown names: {}",
+                version_id,
+                report.errors().join("; ")
+            )
+            .into());
+        }
+        Ok(report)
+    }
+
+    /// Names of the variables set for every applicant
+    pub fn variable_names() -> impl Iterator<Item = &'static str> {
+        VARIABLES.iter().map(|(name, _)| *name)
+    }
+
+    /// Provided variables the loaded formulas never read
+    pub fn unused_variables(&self) -> &[String] {
+        &self.lint.unused_variables
+    }
+
+    /// Formulas the final premium only reads through another formula, mapped to that formula
+    pub fn sub_factors(&self) -> &BTreeMap<String, String> {
+        &self.sub_factors
+    }
+
+    /// Loaded formulas that no result formula depends on
+    pub fn unused_formulas(&self) -> &[String] {
+        &self.unused_formulas
+    }
+
+    /// Compile another formula set, keeping the same tables and rules
+    /// Fails when the set lacks a formula the underwriting rules rate on
+    pub fn rebuild(
         &self,
-        engine: &Engine,
-        _applicant: &Applicant,
-    ) -> Result<CalculationResults, Box<dyn Error>> {
-        Ok(CalculationResults {
-            base_premium: self.extract_number(engine, "base_premium")?,
-            age_factor: self.extract_number(engine, "age_factor")?,
-            health_risk_score: self.extract_number(engine, "health_risk_score")?,
-            lifestyle_multiplier: self.extract_number(engine, "lifestyle_multiplier")?,
-            occupation_factor: self.extract_number(engine, "occupation_factor")?,
-            final_premium: self.extract_number(engine, "final_premium")?,
+        formula_repository: Box<dyn FormulaRepository>,
+    ) -> Result<Self, Box<dyn Error>> {
+        self.rebuild_from(Arc::from(formula_repository))
+    }
+
+    /// Read and compile the formula scripts again into a new service, picking up edits made
+    /// since loading; this service keeps rating with the formulas it has
+    pub fn reloaded(&self) -> Result<Self, Box<dyn Error>> {
+        self.rebuild_from(self.formula_repository.clone())
+    }
+
+    fn rebuild_from(
+        &self,
+        formula_repository: Arc<dyn FormulaRepository>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let loaded = Self::load(
+            formula_repository.as_ref(),
+            &self.occupations,
+            &self.base_rates,
+        )?;
+        let formula_names: Vec<String> = loaded.plan.formula_names().map(str::to_string).collect();
+        self.underwriting
+            .check_rated_outputs(&formula_names)
+            .map_err(|e| format!("{} in plan version {}", e, formula_repository.version_id()))?;
+        Ok(Self {
+            formula_repository,
+            occupations: self.occupations.clone(),
+            base_rates: self.base_rates.clone(),
+            underwriting: self.underwriting.clone(),
+            modal_factors: self.modal_factors,
+            plan: loaded.plan,
+            lint: loaded.lint,
+            unused_formulas: loaded.unused_formulas,
+            sub_factors: loaded.sub_factors,
+            explain: self.explain,
         })
     }
 
-    fn extract_number(&self, engine: &Engine, name: &str) -> Result<f64, Box<dyn Error>> {
-        match engine
-            .get_result(name)
-            .ok_or(format!("{} not found", name))?
-        {
-            Value::Number(n) => Ok(n),
-            _ => Err(format!("{} is not a number", name).into()),
+    /// Version id of the formula set, recorded on every result
+    pub fn plan_version(&self) -> &str {
+        self.formula_repository.version_id()
+    }
+
+    /// Directory the formula scripts were loaded from, if any
+    pub fn formula_directory(&self) -> Option<&Path> {
+        self.formula_repository.directory()
+    }
+
+    /// Names of the loaded formulas in execution order
+    pub fn formula_names(&self) -> Vec<String> {
+        self.plan.formula_names().map(str::to_string).collect()
+    }
+
+    /// Calculate premium for a single applicant using the compiled rating plan
+    pub fn calculate(&self, applicant: &Applicant) -> Result<PremiumResult, FormulaError> {
+        self.calculate_with_plan(&self.plan, applicant)
+    }
+
+    /// Calculate premium reloading and recompiling every formula for this applicant
+    /// Reference path used by benchmark mode to measure the compiled plan's speedup
+    pub fn calculate_uncompiled(
+        &self,
+        applicant: &Applicant,
+    ) -> Result<PremiumResult, Box<dyn Error>> {
+        let plan = Self::compile(
+            self.formula_repository.load_all()?,
+            &self.occupations,
+            &self.base_rates,
+        )?;
+        Ok(self.calculate_with_plan(&plan, applicant)?)
+    }
+
+    /// Evaluate a single formula with hand-picked inputs, as a golden test case does
+    /// Inputs name variables, or formulas whose output is used instead of evaluating them
+    pub fn evaluate_formula(
+        &self,
+        name: &str,
+        inputs: &BTreeMap<String, Value>,
+    ) -> Result<Value, FormulaError> {
+        let variables = VariableCache::new();
+        let mut given = BTreeMap::new();
+        for (input, value) in inputs {
+            if self.plan.formula_names().any(|formula| formula == input) {
+                given.insert(input.clone(), value.clone());
+            } else if Self::variable_names().any(|variable| variable == input) {
+                variables.set(input.clone(), value.clone());
+            } else {
+                return Err(FormulaError {
+                    formula: name.to_string(),
+                    message: format!("input '{}' is neither a variable nor a formula", input),
+                });
+            }
         }
+        self.plan.evaluate_formula(name, variables, &given)
     }
-}
 
-/// Internal struct for holding calculation results
-struct CalculationResults {
-    base_premium: f64,
-    age_factor: f64,
-    health_risk_score: f64,
-    lifestyle_multiplier: f64,
-    occupation_factor: f64,
-    final_premium: f64,
+    fn calculate_with_plan(
+        &self,
+        plan: &RatingPlan,
+        applicant: &Applicant,
+    ) -> Result<PremiumResult, FormulaError> {
+        let start = Instant::now();
+
+        let variables = self.build_variables(applicant);
+        let (outputs, trace) = if self.explain {
+            plan.evaluate_traced(variables)?
+        } else {
+            (plan.evaluate(variables)?, Vec::new())
+        };
+
+        let outputs = self.extract_outputs(plan, &outputs);
+        let base_premium = self.required_output(&outputs, "base_premium")?;
+        let final_premium = self.required_output(&outputs, "final_premium")?;
+        let calculation_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        let mut premium_result = PremiumResult::new(
+            applicant.clone(),
+            base_premium,
+            final_premium,
+            outputs,
+            calculation_time_ms,
+        );
+        premium_result.warnings = self.collect_warnings(applicant);
+        // Payment options first, so affordability is judged on what billing charges
+        premium_result.payment_options = self
+            .modal_factors
+            .payment_options(premium_result.rated_annual_premium());
+        premium_result.underwriting = self.underwriting.assess(&premium_result);
+        premium_result.plan_version = self.plan_version().to_string();
+        premium_result.trace = trace;
+        Ok(premium_result)
+    }
+
+    /// Flag applicant data the rating tables could not classify
+    fn collect_warnings(&self, applicant: &Applicant) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.occupations.class_of(&applicant.occupation).is_none() {
+            warnings.push(format!(
+                "Occupation '{}' is not in the occupation risk table",
+                applicant.occupation
+            ));
+        }
+        warnings
+    }
+
+    /// Build the formula variables from applicant data
+    fn build_variables(&self, applicant: &Applicant) -> VariableCache {
+        let variables = VariableCache::new();
+        for (name, read) in VARIABLES {
+            variables.set(name.to_string(), read(applicant));
+        }
+        variables
+    }
+
+    /// Capture every numeric formula output in execution order
+    fn extract_outputs(&self, plan: &RatingPlan, results: &FormulaResultCache) -> FormulaOutputs {
+        plan.formula_names()
+            .filter_map(|name| match results.get(name) {
+                Some(Value::Number(n)) => Some((name.to_string(), n)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn required_output(&self, outputs: &FormulaOutputs, name: &str) -> Result<f64, FormulaError> {
+        outputs.get(name).ok_or_else(|| FormulaError {
+            formula: name.to_string(),
+            message: "output not found or not a number".to_string(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::repository::InMemoryFormulaRepository;
+    use crate::domain::{Decision, Gender, PaymentMode, SmokerStatus};
+    use crate::repository::{FileFormulaRepository, InMemoryFormulaRepository, BUILT_IN_VERSION};
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     fn create_test_applicant() -> Applicant {
         Applicant {
             id: 1,
             name: "John Doe".to_string(),
             age: 35,
-            gender: "M".to_string(),
-            smoker: false,
+            gender: Gender::Male,
+            smoker: SmokerStatus::NonSmoker,
             occupation: "Engineer".to_string(),
             annual_income: 75000.0,
             bmi: 22.5,
             blood_pressure_sys: 120,
             blood_pressure_dia: 80,
             cholesterol: 180,
-            existing_conditions: "none".to_string(),
+            existing_conditions: Vec::new(),
             family_history_score: 2,
             coverage_amount: 500000.0,
             coverage_years: 20,
@@ -148,7 +448,7 @@ mod tests {
     #[test]
     fn test_calculate_premium_healthy_applicant() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
         let applicant = create_test_applicant();
 
         let result = service.calculate(&applicant);
@@ -164,50 +464,209 @@ mod tests {
     #[test]
     fn test_calculate_premium_smoker() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
         let mut applicant = create_test_applicant();
-        applicant.smoker = true;
+        applicant.smoker = SmokerStatus::Smoker;
 
+        // Smoking is priced by the base rate, not loaded again by the lifestyle multiplier
         let result = service.calculate(&applicant).unwrap();
-        assert!(result.lifestyle_multiplier >= 1.8);
+        let non_smoker = service.calculate(&create_test_applicant()).unwrap();
+        assert!(result.base_premium > non_smoker.base_premium);
+        assert_eq!(result.outputs.get("lifestyle_multiplier"), Some(1.0));
     }
 
     #[test]
     fn test_calculate_premium_with_conditions() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let mut applicant = create_test_applicant();
+        applicant.existing_conditions = vec![Condition::Diabetes];
+
+        let result = service.calculate(&applicant).unwrap();
+        assert!(result.outputs.get("lifestyle_multiplier").unwrap() >= 1.6);
+    }
+
+    #[test]
+    fn test_condition_loadings_are_condition_specific() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let loading = |conditions: &[Condition]| {
+            let mut applicant = create_test_applicant();
+            applicant.existing_conditions = conditions.to_vec();
+            service
+                .calculate(&applicant)
+                .unwrap()
+                .outputs
+                .get("condition_loading")
+                .unwrap()
+        };
+
+        assert_eq!(loading(&[]), 1.0);
+        assert_eq!(loading(&[Condition::Diabetes]), 1.6);
+        assert_eq!(loading(&[Condition::Arthritis]), 1.2);
+        assert!((loading(&[Condition::Asthma, Condition::Arthritis]) - 1.5).abs() < 1e-9);
+        // Diabetes, hypertension and asthma add up to 2.3 and are capped
+        assert_eq!(
+            loading(&[
+                Condition::Diabetes,
+                Condition::Hypertension,
+                Condition::Asthma
+            ]),
+            2.0
+        );
+    }
+
+    #[test]
+    fn test_smoking_does_not_compound_condition_loading() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
         let mut applicant = create_test_applicant();
-        applicant.existing_conditions = "diabetes".to_string();
+        applicant.smoker = SmokerStatus::Smoker;
+        applicant.existing_conditions = vec![Condition::Arthritis];
 
         let result = service.calculate(&applicant).unwrap();
-        assert!(result.lifestyle_multiplier >= 1.6);
+        assert_eq!(result.outputs.get("lifestyle_multiplier"), Some(1.2));
     }
 
     #[test]
-    fn test_calculate_premium_age_factors() {
+    fn test_other_condition_uses_default_loading() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let mut applicant = create_test_applicant();
+        applicant.existing_conditions = vec![Condition::Other];
 
-        let mut young = create_test_applicant();
-        young.age = 25;
-        let young_result = service.calculate(&young).unwrap();
-        assert_eq!(young_result.age_factor, 1.0);
+        let result = service.calculate(&applicant).unwrap();
+        assert_eq!(result.outputs.get("condition_loading"), Some(1.6));
+        assert!(result.warnings.is_empty());
+    }
 
-        let mut middle = create_test_applicant();
-        middle.age = 45;
-        let middle_result = service.calculate(&middle).unwrap();
-        assert_eq!(middle_result.age_factor, 1.5);
+    #[test]
+    fn test_base_premium_uses_rate_table() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
 
-        let mut senior = create_test_applicant();
-        senior.age = 65;
-        let senior_result = service.calculate(&senior).unwrap();
-        assert_eq!(senior_result.age_factor, 2.8);
+        let mut female = create_test_applicant();
+        female.gender = Gender::Female;
+        assert_eq!(service.calculate(&female).unwrap().base_premium, 212.5);
+
+        let table = "age_min,age_max,gender,smoker,term_min,term_max,rate\n18,85,M,no,1,40,1.0\n";
+        let path = crate::test_support::temp_dir("rates").join("rates.csv");
+        std::fs::write(&path, table).unwrap();
+        let service =
+            service.with_base_rate_table(BaseRateTable::from_csv(&path.to_string_lossy()).unwrap());
+        assert_eq!(
+            service
+                .calculate(&create_test_applicant())
+                .unwrap()
+                .base_premium,
+            500.0
+        );
+
+        let err = service.calculate(&female).unwrap_err();
+        assert_eq!(err.formula, "base_premium");
+        assert!(err.message.contains("no base rate"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_age_smoker_and_term_move_base_premium() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let base_premium =
+            |applicant: &Applicant| service.calculate(applicant).unwrap().base_premium;
+        let standard = create_test_applicant();
+        assert_eq!(base_premium(&standard), 250.0);
+
+        let mut older = create_test_applicant();
+        older.age = 55;
+        assert_eq!(base_premium(&older), 337.5);
+
+        let mut smoker = create_test_applicant();
+        smoker.smoker = SmokerStatus::Smoker;
+        assert_eq!(base_premium(&smoker), 325.0);
+
+        let mut longer_term = create_test_applicant();
+        longer_term.coverage_years = 30;
+        assert_eq!(base_premium(&longer_term), 275.0);
+    }
+
+    #[test]
+    fn test_age_smoker_and_term_are_priced_once() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let mut applicant = create_test_applicant();
+        applicant.age = 55;
+        applicant.smoker = SmokerStatus::Smoker;
+
+        // 439 base from the 50-59 male smoker rate x 1.2 health; no other factor applies
+        let result = service.calculate(&applicant).unwrap();
+        assert_eq!(result.base_premium, 439.0);
+        assert_eq!(result.final_premium, 526.8);
+        assert_eq!(result.outputs.get("lifestyle_multiplier"), Some(1.0));
+    }
+
+    #[test]
+    fn test_result_carries_underwriting_decision() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+
+        let standard = service.calculate(&create_test_applicant()).unwrap();
+        assert_eq!(standard.underwriting.decision, Decision::Standard);
+
+        let mut applicant = create_test_applicant();
+        applicant.blood_pressure_sys = 185;
+        let declined = service.calculate(&applicant).unwrap();
+        assert_eq!(declined.underwriting.decision, Decision::Decline);
+        assert!(declined.final_premium > 0.0);
+    }
+
+    #[test]
+    fn test_result_carries_payment_options() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let result = service.calculate(&create_test_applicant()).unwrap();
+
+        let annual = result.payment(PaymentMode::Annual).unwrap();
+        let monthly = result.payment(PaymentMode::Monthly).unwrap();
+        assert_eq!(result.payment_options.len(), 4);
+        assert!(annual.annual_total < result.rated_annual_premium());
+        assert!(monthly.annual_total > result.rated_annual_premium());
+    }
+
+    #[test]
+    fn test_result_records_plan_version() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let result = service.calculate(&create_test_applicant()).unwrap();
+        assert_eq!(result.plan_version, BUILT_IN_VERSION);
+
+        let formula_repo = Box::new(
+            FileFormulaRepository::new(concat!(env!("CARGO_MANIFEST_DIR"), "/formulas"))
+                .with_version("2025-01"),
+        );
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let result = service.calculate(&create_test_applicant()).unwrap();
+        assert_eq!(result.plan_version, "2025-01");
+    }
+
+    #[test]
+    fn test_base_premium_rises_with_age() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let base_premium = |age| {
+            let mut applicant = create_test_applicant();
+            applicant.age = age;
+            service.calculate(&applicant).unwrap().base_premium
+        };
+
+        assert_eq!(base_premium(25), 225.0);
+        assert_eq!(base_premium(45), 287.5);
+        assert_eq!(base_premium(65), 400.0);
     }
 
     #[test]
     fn test_base_premium_calculation() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
         let applicant = create_test_applicant();
 
         let result = service.calculate(&applicant).unwrap();
@@ -218,7 +677,7 @@ mod tests {
     #[test]
     fn test_health_risk_score_healthy() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
         let mut applicant = create_test_applicant();
         // Set to optimal blood pressure
         applicant.blood_pressure_sys = 110;
@@ -226,13 +685,13 @@ mod tests {
 
         let result = service.calculate(&applicant).unwrap();
         // Healthy applicant should have health_risk_score of 1.0
-        assert_eq!(result.health_risk_score, 1.0);
+        assert_eq!(result.outputs.get("health_risk_score"), Some(1.0));
     }
 
     #[test]
     fn test_high_bmi_increases_premium() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
 
         let mut normal = create_test_applicant();
         normal.bmi = 23.0;
@@ -248,7 +707,7 @@ mod tests {
     #[test]
     fn test_high_cholesterol_increases_premium() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
 
         let mut normal = create_test_applicant();
         normal.cholesterol = 190;
@@ -258,13 +717,16 @@ mod tests {
         high.cholesterol = 250;
         let high_result = service.calculate(&high).unwrap();
 
-        assert!(high_result.health_risk_score > normal_result.health_risk_score);
+        assert!(
+            high_result.outputs.get("health_risk_score").unwrap()
+                > normal_result.outputs.get("health_risk_score").unwrap()
+        );
     }
 
     #[test]
-    fn test_duration_discount() {
+    fn test_longer_terms_cost_more() {
         let formula_repo = Box::new(InMemoryFormulaRepository::new());
-        let service = PremiumCalculationService::new(formula_repo);
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
 
         let mut short_term = create_test_applicant();
         short_term.coverage_years = 10;
@@ -274,7 +736,301 @@ mod tests {
         long_term.coverage_years = 30;
         let long_result = service.calculate(&long_term).unwrap();
 
-        // 30-year coverage gets 5% discount
-        assert!(long_result.final_premium < short_result.final_premium);
+        // The base rate rises with the term; there is no separate duration discount
+        assert!(long_result.final_premium > short_result.final_premium);
+        assert_eq!(long_result.outputs.get("duration_discount"), None);
+    }
+
+    #[test]
+    fn test_all_formula_outputs_captured() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let mut applicant = create_test_applicant();
+        applicant.family_history_score = 5;
+        applicant.coverage_years = 30;
+
+        let result = service.calculate(&applicant).unwrap();
+        assert_eq!(result.outputs.iter().count(), 10);
+        assert_eq!(result.outputs.get("family_history_factor"), Some(1.3));
+    }
+
+    #[test]
+    fn test_direct_factors_multiply_to_final_premium() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let result = service.calculate(&create_test_applicant()).unwrap();
+
+        let product = [
+            "base_premium",
+            "health_risk_score",
+            "lifestyle_multiplier",
+            "family_history_factor",
+            "occupation_factor",
+        ]
+        .iter()
+        .map(|name| result.outputs.get(name).unwrap())
+        .product::<f64>();
+        assert!((product - result.final_premium).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_uncompiled_matches_compiled() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let applicant = create_test_applicant();
+
+        let compiled = service.calculate(&applicant).unwrap();
+        let uncompiled = service.calculate_uncompiled(&applicant).unwrap();
+        assert_eq!(compiled.final_premium, uncompiled.final_premium);
+    }
+
+    #[test]
+    fn test_new_rejects_unresolvable_formulas() {
+        struct BrokenRepository;
+        impl FormulaRepository for BrokenRepository {
+            fn load_all(&self) -> Result<Vec<formcalc::Formula>, Box<dyn Error>> {
+                Ok(vec![formcalc::Formula::new(
+                    "final_premium",
+                    "return get_output_from('missing')",
+                )])
+            }
+        }
+
+        assert!(PremiumCalculationService::new(Box::new(BrokenRepository)).is_err());
+    }
+
+    #[test]
+    fn test_new_rejects_unknown_variables() {
+        struct TypoRepository;
+        impl FormulaRepository for TypoRepository {
+            fn load_all(&self) -> Result<Vec<formcalc::Formula>, Box<dyn Error>> {
+                Ok(vec![
+                    formcalc::Formula::new("base_premium", "return 100"),
+                    formcalc::Formula::new(
+                        "final_premium",
+                        "if (age > 90) then return cholestrol else return 1 end",
+                    ),
+                ])
+            }
+        }
+
+        let err = PremiumCalculationService::new(Box::new(TypoRepository))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("unknown variable 'cholestrol'"), "{}", err);
+    }
+
+    #[test]
+    fn test_every_rated_condition_has_a_variable() {
+        let names: Vec<&str> = PremiumCalculationService::variable_names().collect();
+        for condition in Condition::RATED {
+            assert!(names.contains(&condition.as_str()), "{}", condition);
+        }
+
+        let service =
+            PremiumCalculationService::new(Box::new(InMemoryFormulaRepository::new())).unwrap();
+        assert_eq!(
+            service.unused_variables(),
+            ["annual_income", "condition_count", "has_conditions"]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_formula_with_given_inputs() {
+        let service =
+            PremiumCalculationService::new(Box::new(InMemoryFormulaRepository::new())).unwrap();
+        let inputs = |pairs: &[(&str, Value)]| -> BTreeMap<String, Value> {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect()
+        };
+
+        let family = service.evaluate_formula(
+            "family_history_factor",
+            &inputs(&[("family_history_score", 4.0.into())]),
+        );
+        assert_eq!(family.unwrap(), Value::Number(1.15));
+
+        // Component risks are given, so no health data is needed
+        let score = service.evaluate_formula(
+            "health_risk_score",
+            &inputs(&[
+                ("bmi_risk", 1.3.into()),
+                ("bp_risk", 1.2.into()),
+                ("cholesterol_risk", 1.0.into()),
+            ]),
+        );
+        assert_eq!(score.unwrap(), Value::Number(1.56));
+
+        let err = service
+            .evaluate_formula(
+                "family_history_factor",
+                &inputs(&[("family_history", 4.0.into())]),
+            )
+            .unwrap_err();
+        assert!(err.message.contains("'family_history'"), "{}", err);
+    }
+
+    #[test]
+    fn test_explain_mode_records_branches() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let mut applicant = create_test_applicant();
+        applicant.family_history_score = 4;
+        assert!(service.calculate(&applicant).unwrap().trace.is_empty());
+
+        let result = service.with_explain(true).calculate(&applicant).unwrap();
+        assert_eq!(result.trace.len(), result.outputs.iter().count());
+        let family = result
+            .trace
+            .iter()
+            .find(|trace| trace.formula == "family_history_factor")
+            .unwrap();
+        assert_eq!(
+            family.branch_label().as_deref(),
+            Some("family_history_score <= 4")
+        );
+        assert_eq!(family.inputs_label(), "family_history_score = 4");
+        assert_eq!(family.output, "1.15");
+
+        let occupation = result
+            .trace
+            .iter()
+            .find(|trace| trace.formula == "occupation_factor")
+            .unwrap();
+        assert_eq!(
+            occupation.branch,
+            vec!["occupation_class(occupation) = 'office'"]
+        );
+        assert_eq!(occupation.inputs_label(), "occupation = 'Engineer'");
+    }
+
+    /// Built-in formulas plus one no result uses, counting how often they are read
+    struct CountingRepository {
+        loads: AtomicUsize,
+    }
+
+    impl FormulaRepository for CountingRepository {
+        fn load_all(&self) -> Result<Vec<Formula>, Box<dyn Error>> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            let mut formulas = InMemoryFormulaRepository::new().load_all()?;
+            formulas.push(Formula::new("spare", "return 1"));
+            Ok(formulas)
+        }
+    }
+
+    #[test]
+    fn test_formulas_are_read_once_per_load() {
+        let service =
+            PremiumCalculationService::new(Box::new(InMemoryFormulaRepository::new())).unwrap();
+        let repository = Arc::new(CountingRepository {
+            loads: AtomicUsize::new(0),
+        });
+
+        let rebuilt = service.rebuild_from(repository.clone()).unwrap();
+        assert_eq!(repository.loads.load(Ordering::SeqCst), 1);
+        assert_eq!(rebuilt.unused_formulas(), ["spare"]);
+    }
+
+    #[test]
+    fn test_reload_picks_up_edited_scripts() {
+        let dir = crate::test_support::temp_dir("reload");
+        crate::test_support::write_formulas(&dir, "return 120");
+
+        let service =
+            PremiumCalculationService::new(Box::new(FileFormulaRepository::new(&dir))).unwrap();
+        let applicant = create_test_applicant();
+        assert_eq!(service.calculate(&applicant).unwrap().final_premium, 120.0);
+
+        std::fs::write(dir.join("final_premium.fc"), "return 150").unwrap();
+        let reloaded = service.reloaded().unwrap();
+        assert_eq!(reloaded.calculate(&applicant).unwrap().final_premium, 150.0);
+        assert_eq!(service.calculate(&applicant).unwrap().final_premium, 120.0);
+
+        // A broken script is reported and the last good formulas keep rating
+        std::fs::write(dir.join("final_premium.fc"), "return (").unwrap();
+        assert!(reloaded.reloaded().is_err());
+        assert_eq!(reloaded.calculate(&applicant).unwrap().final_premium, 150.0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_rejects_dropped_rated_output() {
+        let dir = crate::test_support::temp_dir("reload_rated_output");
+        crate::test_support::write_formulas(&dir, "return 120");
+        let service =
+            PremiumCalculationService::new(Box::new(FileFormulaRepository::new(&dir))).unwrap();
+
+        std::fs::remove_file(dir.join("lifestyle_multiplier.fc")).unwrap();
+        let err = service.reloaded().err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("rated_outputs names unknown formulas: lifestyle_multiplier"),
+            "{}",
+            err
+        );
+        assert_eq!(
+            service
+                .calculate(&create_test_applicant())
+                .unwrap()
+                .final_premium,
+            120.0
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_underwriting_rules_are_checked_against_the_formulas() {
+        let service =
+            PremiumCalculationService::new(Box::new(InMemoryFormulaRepository::new())).unwrap();
+        let mut rules = UnderwritingRules::default();
+        rules.rated_outputs.insert("bmi_risks".to_string(), 1.2);
+
+        let err = service.with_underwriting_rules(rules).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "rated_outputs names unknown formulas: bmi_risks in plan version {}",
+                BUILT_IN_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn test_occupation_risk_classes() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+
+        let mut clerk = create_test_applicant();
+        clerk.occupation = "Clerk".to_string();
+        let clerk_result = service.calculate(&clerk).unwrap();
+        assert_eq!(clerk_result.outputs.get("occupation_factor"), Some(1.0));
+        assert!(clerk_result.warnings.is_empty());
+
+        let mut electrician = create_test_applicant();
+        electrician.occupation = "Electrician".to_string();
+        let electrician_result = service.calculate(&electrician).unwrap();
+        assert_eq!(
+            electrician_result.outputs.get("occupation_factor"),
+            Some(1.5)
+        );
+        assert!(electrician_result.final_premium > clerk_result.final_premium);
+    }
+
+    #[test]
+    fn test_unknown_occupation_is_flagged() {
+        let formula_repo = Box::new(InMemoryFormulaRepository::new());
+        let service = PremiumCalculationService::new(formula_repo).unwrap();
+        let mut applicant = create_test_applicant();
+        applicant.occupation = "Astronaut".to_string();
+
+        let result = service.calculate(&applicant).unwrap();
+        assert_eq!(result.outputs.get("occupation_factor"), Some(1.25));
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("Astronaut"));
     }
 }
@@ -0,0 +1,374 @@
+use crate::domain::FormulaTrace;
+use crate::services::dependency_graph::DependencyGraph;
+use crate::services::explain;
+use formcalc::cache::{FormulaResultCache, FunctionCache, FunctionResultCache, VariableCache};
+use formcalc::function::build_function_id;
+use formcalc::parser::{Evaluator, Parser, Program};
+use formcalc::{Formula, FormulaT, Function, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Error raised when a formula cannot be evaluated for an applicant
+#[derive(Debug, Clone)]
+pub struct FormulaError {
+    pub formula: String,
+    pub message: String,
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error executing formula '{}': {}",
+            self.formula, self.message
+        )
+    }
+}
+
+impl Error for FormulaError {}
+
+/// A formula parsed once, ready to be evaluated against any set of variables
+struct CompiledFormula {
+    name: String,
+    depends_on: Vec<String>,
+    program: Program,
+}
+
+/// Formula set compiled once per run and evaluated per applicant
+/// Formulas are parsed and ordered by dependency up front, so evaluating an
+/// applicant only swaps the variables
+pub struct RatingPlan {
+    steps: Vec<CompiledFormula>,
+    functions: FunctionCache,
+}
+
+impl RatingPlan {
+    /// Parse all formulas and resolve their execution order
+    pub fn compile(formulas: Vec<Formula>) -> Result<Self, Box<dyn Error>> {
+        let mut steps = Vec::with_capacity(formulas.len());
+        for formula in Self::execution_order(formulas)? {
+            let program = Parser::new(formula.body())
+                .and_then(|mut parser| parser.parse())
+                .map_err(|e| format!("formula '{}': {}", formula.name(), e))?;
+            steps.push(CompiledFormula {
+                name: formula.name().to_string(),
+                depends_on: formula.depends_on().to_vec(),
+                program,
+            });
+        }
+
+        Ok(Self {
+            steps,
+            functions: FunctionCache::new(),
+        })
+    }
+
+    /// Order formulas so every formula runs after the formulas it depends on
+    /// Ties are broken by name so the order is deterministic
+    fn execution_order(formulas: Vec<Formula>) -> Result<Vec<Formula>, Box<dyn Error>> {
+        let graph = DependencyGraph::new(&formulas);
+        let mut pending: BTreeMap<String, Formula> = BTreeMap::new();
+        for formula in formulas {
+            let name = formula.name().to_string();
+            if pending.insert(name.clone(), formula).is_some() {
+                return Err(format!("formula '{}' is defined more than once", name).into());
+            }
+        }
+
+        if let Some(missing) = graph.missing_references().first() {
+            return Err(missing.to_string().into());
+        }
+        if let Some(cycle) = graph.cycles().first() {
+            return Err(format!(
+                "circular dependency between formulas: {}",
+                cycle.join(" -> ")
+            )
+            .into());
+        }
+
+        let mut ordered: Vec<Formula> = Vec::with_capacity(pending.len());
+        let mut done: HashSet<String> = HashSet::new();
+        while !pending.is_empty() {
+            let ready = pending
+                .values()
+                .find(|formula| formula.depends_on().iter().all(|dep| done.contains(dep)))
+                .map(|formula| formula.name().to_string());
+
+            match ready {
+                Some(name) => {
+                    let formula = pending.remove(&name).ok_or(format!("{} not found", name))?;
+                    done.insert(name);
+                    ordered.push(formula);
+                }
+                None => {
+                    let names: Vec<&str> = pending.keys().map(String::as_str).collect();
+                    return Err(format!(
+                        "circular dependency between formulas: {}",
+                        names.join(", ")
+                    )
+                    .into());
+                }
+            }
+        }
+
+        Ok(ordered)
+    }
+
+    /// Make a lookup function callable from the formulas
+    /// Function results are cached per applicant by name, so a lookup must be called
+    /// with the same arguments everywhere it appears in a formula set
+    pub fn register_function(&mut self, function: Arc<dyn Function>) {
+        let function_id = build_function_id(function.name(), function.num_args());
+        self.functions.set(function_id, function);
+    }
+
+    /// Formula names in execution order
+    pub fn formula_names(&self) -> impl Iterator<Item = &str> {
+        self.steps.iter().map(|step| step.name.as_str())
+    }
+
+    /// Evaluate every formula against the given variables
+    pub fn evaluate(&self, variables: VariableCache) -> Result<FormulaResultCache, FormulaError> {
+        self.run(variables, None)
+    }
+
+    /// Evaluate every formula and record how each reached its output, in execution order
+    pub fn evaluate_traced(
+        &self,
+        variables: VariableCache,
+    ) -> Result<(FormulaResultCache, Vec<FormulaTrace>), FormulaError> {
+        let mut trace = Vec::with_capacity(self.steps.len());
+        let results = self.run(variables, Some(&mut trace))?;
+        Ok((results, trace))
+    }
+
+    fn run(
+        &self,
+        variables: VariableCache,
+        mut trace: Option<&mut Vec<FormulaTrace>>,
+    ) -> Result<FormulaResultCache, FormulaError> {
+        let results = FormulaResultCache::new();
+        let evaluator = Evaluator::new(
+            variables.clone(),
+            results.clone(),
+            self.functions.clone(),
+            FunctionResultCache::new(),
+        );
+
+        for step in &self.steps {
+            let value = evaluator
+                .evaluate(&step.program)
+                .map_err(|e| FormulaError {
+                    formula: step.name.clone(),
+                    message: e.to_string(),
+                })?;
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.push(explain::trace(
+                    &step.name,
+                    &step.program,
+                    &evaluator,
+                    &variables,
+                    &results,
+                    &value,
+                ));
+            }
+            results.set(step.name.clone(), value);
+        }
+
+        Ok(results)
+    }
+
+    /// Evaluate one formula and only the formulas it needs
+    /// Outputs in `given` are taken as already computed, so a formula can be checked
+    /// on its own against chosen values of the formulas it reads
+    pub fn evaluate_formula(
+        &self,
+        name: &str,
+        variables: VariableCache,
+        given: &BTreeMap<String, Value>,
+    ) -> Result<Value, FormulaError> {
+        if !self.steps.iter().any(|step| step.name == name) {
+            return Err(FormulaError {
+                formula: name.to_string(),
+                message: "no formula with this name".to_string(),
+            });
+        }
+
+        let mut needed: HashSet<&str> = HashSet::new();
+        let mut pending = vec![name];
+        while let Some(current) = pending.pop() {
+            if (current != name && given.contains_key(current)) || !needed.insert(current) {
+                continue;
+            }
+            if let Some(step) = self.steps.iter().find(|step| step.name == current) {
+                pending.extend(step.depends_on.iter().map(String::as_str));
+            }
+        }
+
+        let results = FormulaResultCache::new();
+        for (formula, value) in given {
+            if formula != name {
+                results.set(formula.clone(), value.clone());
+            }
+        }
+        let evaluator = Evaluator::new(
+            variables,
+            results.clone(),
+            self.functions.clone(),
+            FunctionResultCache::new(),
+        );
+
+        for step in self
+            .steps
+            .iter()
+            .filter(|step| needed.contains(step.name.as_str()))
+        {
+            let value = evaluator
+                .evaluate(&step.program)
+                .map_err(|e| FormulaError {
+                    formula: step.name.clone(),
+                    message: e.to_string(),
+                })?;
+            results.set(step.name.clone(), value);
+        }
+
+        results.get(name).ok_or_else(|| FormulaError {
+            formula: name.to_string(),
+            message: "produced no output".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_orders_by_dependency() {
+        let plan = RatingPlan::compile(vec![
+            Formula::new("total", "return get_output_from('base') * 2"),
+            Formula::new("base", "return x + 1"),
+        ])
+        .unwrap();
+
+        let names: Vec<&str> = plan.formula_names().collect();
+        assert_eq!(names, vec!["base", "total"]);
+    }
+
+    #[test]
+    fn test_compile_orders_multi_level_dependencies() {
+        let plan = RatingPlan::compile(vec![
+            Formula::new(
+                "final",
+                "return get_output_from('score') * get_output_from('a')",
+            ),
+            Formula::new(
+                "score",
+                "return get_output_from('a') + get_output_from('b')",
+            ),
+            Formula::new("a", "return 1"),
+            Formula::new("b", "return 2"),
+        ])
+        .unwrap();
+
+        let names: Vec<&str> = plan.formula_names().collect();
+        assert_eq!(names, vec!["a", "b", "score", "final"]);
+    }
+
+    #[test]
+    fn test_compile_rejects_cycle() {
+        let err = RatingPlan::compile(vec![
+            Formula::new("a", "return get_output_from('b')"),
+            Formula::new("b", "return get_output_from('a')"),
+        ])
+        .err()
+        .unwrap();
+        assert!(err
+            .to_string()
+            .contains("circular dependency between formulas: a -> b -> a"));
+    }
+
+    #[test]
+    fn test_evaluate_swaps_variables() {
+        let plan = RatingPlan::compile(vec![
+            Formula::new("base", "return x + 1"),
+            Formula::new("total", "return get_output_from('base') * 2"),
+        ])
+        .unwrap();
+
+        for x in [1.0, 5.0] {
+            let variables = VariableCache::new();
+            variables.set("x".to_string(), Value::Number(x));
+            let results = plan.evaluate(variables).unwrap();
+            assert_eq!(results.get("total"), Some(Value::Number((x + 1.0) * 2.0)));
+        }
+    }
+
+    #[test]
+    fn test_evaluate_traced_records_every_formula() {
+        let plan = RatingPlan::compile(vec![
+            Formula::new("base", "if (x < 10) then return 1 else return 2 end"),
+            Formula::new("total", "return get_output_from('base') * 2"),
+        ])
+        .unwrap();
+
+        let variables = VariableCache::new();
+        variables.set("x".to_string(), Value::Number(12.0));
+        let (results, trace) = plan.evaluate_traced(variables).unwrap();
+        assert_eq!(results.get("total"), Some(Value::Number(4.0)));
+
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0].branch, vec!["x >= 10"]);
+        assert_eq!(trace[0].inputs, vec![("x".to_string(), "12".to_string())]);
+        assert!(trace[1].branch.is_empty());
+        assert_eq!(trace[1].inputs, vec![("base".to_string(), "2".to_string())]);
+        assert_eq!(trace[1].output, "4");
+    }
+
+    #[test]
+    fn test_compile_rejects_missing_dependency() {
+        let err = RatingPlan::compile(vec![Formula::new(
+            "total",
+            "return get_output_from('missing')",
+        )])
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("total"));
+    }
+
+    #[test]
+    fn test_evaluate_formula_takes_given_outputs() {
+        let plan = RatingPlan::compile(vec![
+            Formula::new("base", "return x + 1"),
+            Formula::new("factor", "return y"),
+            Formula::new(
+                "total",
+                "return get_output_from('base') * get_output_from('factor')",
+            ),
+        ])
+        .unwrap();
+
+        let variables = VariableCache::new();
+        variables.set("x".to_string(), Value::Number(2.0));
+        let given = BTreeMap::from([("factor".to_string(), Value::Number(10.0))]);
+        // 'y' is not set, so this only works if 'factor' is not evaluated
+        assert_eq!(
+            plan.evaluate_formula("total", variables, &given).unwrap(),
+            Value::Number(30.0)
+        );
+
+        let err = plan
+            .evaluate_formula("missing", VariableCache::new(), &BTreeMap::new())
+            .unwrap_err();
+        assert_eq!(err.formula, "missing");
+    }
+
+    #[test]
+    fn test_evaluate_error_names_formula() {
+        let plan = RatingPlan::compile(vec![Formula::new("needs_x", "return x")]).unwrap();
+        let err = plan.evaluate(VariableCache::new()).err().unwrap();
+        assert_eq!(err.formula, "needs_x");
+    }
+}
@@ -0,0 +1,399 @@
+use crate::domain::{Affordability, Decision, PaymentMode, PremiumResult, Underwriting};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+
+/// Built-in underwriting rules, shipped as `data/underwriting_rules.json`
+const DEFAULT_UNDERWRITING_RULES: &str = include_str!("../../data/underwriting_rules.json");
+
+/// Values at or above `refer` go to an underwriter; at or above `decline` they are declined
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Thresholds<T> {
+    pub refer: T,
+    pub decline: T,
+}
+
+impl<T: PartialOrd + Copy + Display> Thresholds<T> {
+    /// A refer limit above the decline limit would never refer anyone
+    fn check_order(&self, field: &str) -> Result<(), String> {
+        if self.refer <= self.decline {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} refer limit {} is above its decline limit {}",
+                field, self.refer, self.decline
+            ))
+        }
+    }
+
+    fn check(&self, field: &str, value: T) -> Option<(Decision, String)> {
+        let (decision, limit) = if value >= self.decline {
+            (Decision::Decline, self.decline)
+        } else if value >= self.refer {
+            (Decision::Refer, self.refer)
+        } else {
+            return None;
+        };
+        Some((
+            decision,
+            format!(
+                "{} {} is at or above the {} limit of {}",
+                field, value, decision, limit
+            ),
+        ))
+    }
+}
+
+/// Maximum coverage as a multiple of annual income, for applicants up to `max_age`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct IncomeMultiple {
+    pub max_age: u32,
+    pub multiple: f64,
+}
+
+/// Configurable rules deciding whether a rated applicant is accepted, referred or declined
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnderwritingRules {
+    pub age: Thresholds<u32>,
+    /// Age when the cover ends, `age + coverage_years`
+    pub age_at_expiry: Thresholds<u32>,
+    pub bmi: Thresholds<f64>,
+    pub blood_pressure_sys: Thresholds<u32>,
+    pub blood_pressure_dia: Thresholds<u32>,
+    pub condition_count: Thresholds<usize>,
+    /// Formula outputs that make an otherwise standard applicant rated when above the given value
+    pub rated_outputs: BTreeMap<String, f64>,
+    /// Age bands in ascending order; the first band covering the applicant's age applies
+    /// Applicants older than every band get no coverage
+    pub income_multiples: Vec<IncomeMultiple>,
+    /// Largest share of annual income the annual premium may take, 0.25 for 25%
+    pub max_premium_to_income: f64,
+}
+
+impl UnderwritingRules {
+    /// Load rules from a JSON file with the same shape as `data/underwriting_rules.json`
+    pub fn from_json(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let json = std::fs::read_to_string(file_path)
+            .map_err(|e| format!("failed to open underwriting rules {}: {}", file_path, e))?;
+        Self::parse(&json).map_err(|e| format!("{}: {}", file_path, e).into())
+    }
+
+    fn parse(json: &str) -> Result<Self, Box<dyn Error>> {
+        let rules: Self = serde_json::from_str(json)?;
+        [
+            rules.age.check_order("age"),
+            rules.age_at_expiry.check_order("age_at_expiry"),
+            rules.bmi.check_order("bmi"),
+            rules.blood_pressure_sys.check_order("blood_pressure_sys"),
+            rules.blood_pressure_dia.check_order("blood_pressure_dia"),
+            rules.condition_count.check_order("condition_count"),
+        ]
+        .into_iter()
+        .collect::<Result<(), String>>()?;
+        Ok(rules)
+    }
+
+    /// Check that every rated output is the name of a formula in the rating plan
+    pub fn check_rated_outputs(&self, formula_names: &[String]) -> Result<(), Box<dyn Error>> {
+        let unknown: Vec<&str> = self
+            .rated_outputs
+            .keys()
+            .filter(|name| !formula_names.contains(name))
+            .map(String::as_str)
+            .collect();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "rated_outputs names unknown formulas: {}",
+                unknown.join(", ")
+            )
+            .into())
+        }
+    }
+
+    /// Coverage multiple of income allowed at an age
+    pub fn income_multiple(&self, age: u32) -> f64 {
+        self.income_multiples
+            .iter()
+            .find(|band| age <= band.max_age)
+            .map(|band| band.multiple)
+            .unwrap_or(0.0)
+    }
+
+    /// Financial limits for a quote: maximum coverage and maximum annual premium
+    /// The premium limit is tested against a year of monthly billing, the mode quotes are
+    /// given in and the most expensive one
+    pub fn affordability(&self, result: &PremiumResult) -> Affordability {
+        let income = result.applicant.annual_income;
+        let max_coverage = income * self.income_multiple(result.applicant.age);
+        let max_annual_premium = income * self.max_premium_to_income;
+        Affordability {
+            max_coverage,
+            max_annual_premium,
+            coverage_exceeded: result.applicant.coverage_amount > max_coverage,
+            premium_exceeded: result.billed_annual(PaymentMode::Monthly) > max_annual_premium,
+        }
+    }
+
+    /// Decide on a rated applicant from their data, formula outputs and premium
+    /// The decision is the most severe one triggered; every triggering reason is kept
+    pub fn assess(&self, result: &PremiumResult) -> Underwriting {
+        let applicant = &result.applicant;
+        let mut triggered: Vec<(Decision, String)> = [
+            self.age.check("age", applicant.age),
+            self.age_at_expiry.check(
+                "age_at_expiry",
+                applicant.age.saturating_add(applicant.coverage_years),
+            ),
+            self.bmi.check("bmi", applicant.bmi),
+            self.blood_pressure_sys
+                .check("blood_pressure_sys", applicant.blood_pressure_sys),
+            self.blood_pressure_dia
+                .check("blood_pressure_dia", applicant.blood_pressure_dia),
+            self.condition_count
+                .check("condition_count", applicant.existing_conditions.len()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        for (name, limit) in &self.rated_outputs {
+            if let Some(value) = result.outputs.get(name).filter(|value| value > limit) {
+                triggered.push((
+                    Decision::Rated,
+                    format!("{} {} is above {}", name, value, limit),
+                ));
+            }
+        }
+
+        // A policy larger than the income supports cannot be issued; an expensive one is reviewed
+        let affordability = self.affordability(result);
+        if affordability.coverage_exceeded {
+            triggered.push((
+                Decision::Decline,
+                format!(
+                    "coverage_amount {} is above the maximum of {:.0} ({}x income)",
+                    applicant.coverage_amount,
+                    affordability.max_coverage,
+                    self.income_multiple(applicant.age)
+                ),
+            ));
+        }
+        if affordability.premium_exceeded {
+            triggered.push((
+                Decision::Refer,
+                format!(
+                    "billed annual premium {:.2} is above the maximum of {:.2} ({}% of income)",
+                    result.billed_annual(PaymentMode::Monthly),
+                    affordability.max_annual_premium,
+                    self.max_premium_to_income * 100.0
+                ),
+            ));
+        }
+
+        Underwriting {
+            decision: triggered
+                .iter()
+                .map(|(decision, _)| *decision)
+                .max()
+                .unwrap_or_default(),
+            reasons: triggered.into_iter().map(|(_, reason)| reason).collect(),
+            affordability,
+        }
+    }
+}
+
+impl Default for UnderwritingRules {
+    fn default() -> Self {
+        Self::parse(DEFAULT_UNDERWRITING_RULES).expect("built-in underwriting rules are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Applicant, Condition, FormulaOutputs};
+    use crate::services::ModalFactors;
+
+    fn applicant() -> Applicant {
+        crate::test_support::applicant(1)
+    }
+
+    /// A 100.00 monthly premium with the given health risk score
+    fn result(applicant: Applicant, health_risk_score: f64) -> PremiumResult {
+        let outputs: FormulaOutputs = vec![
+            ("health_risk_score".to_string(), health_risk_score),
+            ("lifestyle_multiplier".to_string(), 1.0),
+        ]
+        .into_iter()
+        .collect();
+        PremiumResult::new(applicant, 50.0, 100.0, outputs, 0.1)
+    }
+
+    #[test]
+    fn test_healthy_applicant_is_standard() {
+        let underwriting = UnderwritingRules::default().assess(&result(applicant(), 1.0));
+        assert_eq!(underwriting.decision, Decision::Standard);
+        assert!(underwriting.reasons.is_empty());
+    }
+
+    #[test]
+    fn test_loaded_outputs_are_rated() {
+        let underwriting = UnderwritingRules::default().assess(&result(applicant(), 2.4));
+        assert_eq!(underwriting.decision, Decision::Rated);
+        assert_eq!(
+            underwriting.reasons,
+            vec!["health_risk_score 2.4 is above 1.5"]
+        );
+    }
+
+    #[test]
+    fn test_most_severe_decision_wins_and_keeps_every_reason() {
+        let mut applicant = applicant();
+        applicant.age = 75;
+        applicant.coverage_years = 5;
+        applicant.coverage_amount = 250000.0;
+        applicant.blood_pressure_sys = 180;
+        applicant.blood_pressure_dia = 110;
+        applicant.bmi = 40.0;
+        applicant.existing_conditions = vec![Condition::Diabetes, Condition::Hypertension];
+
+        let underwriting = UnderwritingRules::default().assess(&result(applicant, 2.4));
+        assert_eq!(underwriting.decision, Decision::Decline);
+        assert_eq!(
+            underwriting.reasons,
+            vec![
+                "age 75 is at or above the refer limit of 70",
+                "age_at_expiry 80 is at or above the refer limit of 80",
+                "bmi 40 is at or above the refer limit of 40",
+                "blood_pressure_sys 180 is at or above the decline limit of 180",
+                "blood_pressure_dia 110 is at or above the decline limit of 110",
+                "condition_count 2 is at or above the refer limit of 2",
+                "health_risk_score 2.4 is above 1.5",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_age_at_expiry_does_not_overflow() {
+        let mut applicant = applicant();
+        applicant.age = u32::MAX;
+
+        let underwriting = UnderwritingRules::default().assess(&result(applicant, 1.0));
+        assert_eq!(underwriting.decision, Decision::Decline);
+    }
+
+    #[test]
+    fn test_refer_outranks_rated() {
+        let mut applicant = applicant();
+        applicant.age = 72;
+        applicant.coverage_years = 5;
+        applicant.coverage_amount = 250000.0;
+
+        let underwriting = UnderwritingRules::default().assess(&result(applicant, 2.4));
+        assert_eq!(underwriting.decision, Decision::Refer);
+        assert_eq!(underwriting.reasons.len(), 2);
+    }
+
+    #[test]
+    fn test_coverage_above_income_multiple_is_declined() {
+        let mut applicant = applicant();
+        applicant.annual_income = 35000.0;
+        applicant.coverage_amount = 1500000.0;
+
+        let underwriting = UnderwritingRules::default().assess(&result(applicant, 1.0));
+        assert_eq!(underwriting.decision, Decision::Decline);
+        assert_eq!(underwriting.affordability.max_coverage, 1050000.0);
+        assert!(underwriting.affordability.coverage_exceeded);
+        assert!(!underwriting.affordability.premium_exceeded);
+        assert_eq!(
+            underwriting.reasons,
+            vec!["coverage_amount 1500000 is above the maximum of 1050000 (30x income)"]
+        );
+    }
+
+    #[test]
+    fn test_income_multiple_falls_with_age() {
+        let rules = UnderwritingRules::default();
+        assert_eq!(rules.income_multiple(39), 30.0);
+        assert_eq!(rules.income_multiple(40), 25.0);
+        assert_eq!(rules.income_multiple(85), 5.0);
+        assert_eq!(rules.income_multiple(90), 0.0);
+    }
+
+    #[test]
+    fn test_premium_above_income_share_is_referred() {
+        let mut applicant = applicant();
+        applicant.annual_income = 4000.0;
+        applicant.coverage_amount = 100000.0;
+
+        // 1200.00 a year is 30% of income
+        let underwriting = UnderwritingRules::default().assess(&result(applicant, 1.0));
+        assert_eq!(underwriting.decision, Decision::Refer);
+        assert_eq!(underwriting.affordability.max_annual_premium, 1000.0);
+        assert!(underwriting.affordability.premium_exceeded);
+        assert_eq!(
+            underwriting.reasons,
+            vec!["billed annual premium 1200.00 is above the maximum of 1000.00 (25% of income)"]
+        );
+    }
+
+    #[test]
+    fn test_premium_limit_uses_billed_amount() {
+        let mut applicant = applicant();
+        applicant.annual_income = 4850.0;
+        applicant.coverage_amount = 100000.0;
+
+        // 1200.00 rated is within 25% of income, the 1224.00 billed monthly is not
+        let mut result = result(applicant, 1.0);
+        result.payment_options =
+            ModalFactors::default().payment_options(result.rated_annual_premium());
+        let underwriting = UnderwritingRules::default().assess(&result);
+        assert!(underwriting.affordability.premium_exceeded);
+        assert_eq!(
+            underwriting.reasons,
+            vec!["billed annual premium 1224.00 is above the maximum of 1212.50 (25% of income)"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_rule_is_rejected() {
+        let json = DEFAULT_UNDERWRITING_RULES.replace("\"bmi\"", "\"bmii\"");
+        assert!(serde_json::from_str::<UnderwritingRules>(&json).is_err());
+    }
+
+    #[test]
+    fn test_refer_limit_above_decline_is_rejected() {
+        let json = DEFAULT_UNDERWRITING_RULES.replace(
+            "\"bmi\": { \"refer\": 40.0, \"decline\": 45.0 }",
+            "\"bmi\": { \"refer\": 45.0, \"decline\": 40.0 }",
+        );
+        let err = UnderwritingRules::parse(&json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bmi refer limit 45 is above its decline limit 40"
+        );
+    }
+
+    #[test]
+    fn test_rated_outputs_must_name_formulas() {
+        let mut rules = UnderwritingRules::default();
+        let formulas = vec![
+            "health_risk_score".to_string(),
+            "lifestyle_multiplier".to_string(),
+        ];
+        assert!(rules.check_rated_outputs(&formulas).is_ok());
+
+        rules
+            .rated_outputs
+            .insert("health_risk_scor".to_string(), 1.5);
+        let err = rules.check_rated_outputs(&formulas).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rated_outputs names unknown formulas: health_risk_scor"
+        );
+    }
+}
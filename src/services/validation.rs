@@ -0,0 +1,267 @@
+use crate::domain::{Applicant, Gender, Rejection, Violation};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+
+/// Built-in validation rules, shipped as `data/validation_rules.json`
+const DEFAULT_VALIDATION_RULES: &str = include_str!("../../data/validation_rules.json");
+
+/// Inclusive range of accepted values for a field
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Range<T> {
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: PartialOrd + Copy + Display> Range<T> {
+    fn check(&self, field: &str, value: T) -> Option<Violation> {
+        // NaN compares false against both bounds, so it falls outside every range
+        if !(self.min..=self.max).contains(&value) {
+            Some(Violation {
+                rule: field.to_string(),
+                message: format!("{} {} is outside {}..={}", field, value, self.min, self.max),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Configurable checks an applicant must pass before it is rated
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationRules {
+    pub age: Range<u32>,
+    pub bmi: Range<f64>,
+    pub blood_pressure_sys: Range<u32>,
+    pub blood_pressure_dia: Range<u32>,
+    pub cholesterol: Range<u32>,
+    pub family_history_score: Range<u32>,
+    pub annual_income: Range<f64>,
+    pub coverage_amount: Range<f64>,
+    pub coverage_years: Range<u32>,
+    pub allowed_genders: Vec<Gender>,
+    /// Reject readings where the diastolic pressure is not below the systolic one
+    pub diastolic_below_systolic: bool,
+}
+
+impl ValidationRules {
+    /// Load rules from a JSON file with the same shape as `data/validation_rules.json`
+    pub fn from_json(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let json = std::fs::read_to_string(file_path)
+            .map_err(|e| format!("failed to open validation rules {}: {}", file_path, e))?;
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", file_path, e).into())
+    }
+
+    /// Every rule the applicant breaks; empty when the record is valid
+    pub fn check(&self, applicant: &Applicant) -> Vec<Violation> {
+        let mut violations: Vec<Violation> = [
+            self.age.check("age", applicant.age),
+            self.bmi.check("bmi", applicant.bmi),
+            self.blood_pressure_sys
+                .check("blood_pressure_sys", applicant.blood_pressure_sys),
+            self.blood_pressure_dia
+                .check("blood_pressure_dia", applicant.blood_pressure_dia),
+            self.cholesterol.check("cholesterol", applicant.cholesterol),
+            self.family_history_score
+                .check("family_history_score", applicant.family_history_score),
+            self.annual_income
+                .check("annual_income", applicant.annual_income),
+            self.coverage_amount
+                .check("coverage_amount", applicant.coverage_amount),
+            self.coverage_years
+                .check("coverage_years", applicant.coverage_years),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if !self.allowed_genders.contains(&applicant.gender) {
+            violations.push(Violation {
+                rule: "gender".to_string(),
+                message: format!(
+                    "gender '{}' is not one of {}",
+                    applicant.gender,
+                    self.allowed_genders
+                        .iter()
+                        .map(Gender::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            });
+        }
+
+        if self.diastolic_below_systolic
+            && applicant.blood_pressure_dia >= applicant.blood_pressure_sys
+        {
+            violations.push(Violation {
+                rule: "diastolic_below_systolic".to_string(),
+                message: format!(
+                    "diastolic pressure {} is not below systolic pressure {}",
+                    applicant.blood_pressure_dia, applicant.blood_pressure_sys
+                ),
+            });
+        }
+
+        violations
+    }
+
+    /// Check a single applicant, returning the rejection if it breaks any rule
+    pub fn validate(&self, applicant: &Applicant) -> Result<(), Rejection> {
+        let violations = self.check(applicant);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Rejection {
+                applicant_id: applicant.id,
+                violations,
+            })
+        }
+    }
+
+    /// Separate valid applicants from rejected ones, keeping the input order
+    pub fn split(&self, applicants: Vec<Applicant>) -> ValidationReport {
+        let mut report = ValidationReport {
+            valid: Vec::with_capacity(applicants.len()),
+            rejected: Vec::new(),
+        };
+        for applicant in applicants {
+            match self.validate(&applicant) {
+                Ok(()) => report.valid.push(applicant),
+                Err(rejection) => report.rejected.push(rejection),
+            }
+        }
+        report
+    }
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        serde_json::from_str(DEFAULT_VALIDATION_RULES).expect("built-in validation rules are valid")
+    }
+}
+
+/// Outcome of validating a batch of applicants
+pub struct ValidationReport {
+    pub valid: Vec<Applicant>,
+    pub rejected: Vec<Rejection>,
+}
+
+impl ValidationReport {
+    /// Number of rejected records breaking each rule; a record can break several rules
+    pub fn counts_by_rule(&self) -> BTreeMap<&str, usize> {
+        counts_by_rule(&self.rejected)
+    }
+}
+
+/// Number of rejections breaking each rule
+pub fn counts_by_rule(rejections: &[Rejection]) -> BTreeMap<&str, usize> {
+    let mut counts = BTreeMap::new();
+    for violation in rejections.iter().flat_map(|r| &r.violations) {
+        *counts.entry(violation.rule.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applicant() -> Applicant {
+        crate::test_support::applicant(1)
+    }
+
+    fn rules_broken(applicant: &Applicant) -> Vec<String> {
+        ValidationRules::default()
+            .check(applicant)
+            .into_iter()
+            .map(|violation| violation.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_applicant_passes() {
+        assert!(ValidationRules::default().validate(&applicant()).is_ok());
+    }
+
+    #[test]
+    fn test_range_violations() {
+        let mut applicant = applicant();
+        applicant.age = 0;
+        applicant.bmi = 400.0;
+        applicant.coverage_amount = -1.0;
+        applicant.family_history_score = 99;
+
+        assert_eq!(
+            rules_broken(&applicant),
+            vec!["age", "bmi", "family_history_score", "coverage_amount"]
+        );
+    }
+
+    #[test]
+    fn test_non_finite_values_are_rejected() {
+        let mut applicant = applicant();
+        applicant.bmi = f64::NAN;
+        applicant.annual_income = f64::INFINITY;
+        applicant.coverage_amount = f64::NEG_INFINITY;
+
+        assert_eq!(
+            rules_broken(&applicant),
+            vec!["bmi", "annual_income", "coverage_amount"]
+        );
+    }
+
+    #[test]
+    fn test_diastolic_above_systolic() {
+        let mut applicant = applicant();
+        applicant.blood_pressure_dia = 130;
+        applicant.blood_pressure_sys = 120;
+        assert_eq!(rules_broken(&applicant), vec!["diastolic_below_systolic"]);
+    }
+
+    #[test]
+    fn test_disallowed_gender() {
+        let rules = ValidationRules {
+            allowed_genders: vec![Gender::Female],
+            ..ValidationRules::default()
+        };
+
+        let rejection = rules.validate(&applicant()).unwrap_err();
+        assert_eq!(rejection.applicant_id, 1);
+        assert!(rejection.to_string().contains("gender 'M' is not one of F"));
+    }
+
+    #[test]
+    fn test_split_keeps_order_and_counts_rules() {
+        let mut too_young = applicant();
+        too_young.id = 2;
+        too_young.age = 5;
+        let mut third = applicant();
+        third.id = 3;
+
+        let report = ValidationRules::default().split(vec![applicant(), too_young, third]);
+        let valid_ids: Vec<u32> = report.valid.iter().map(|a| a.id).collect();
+        assert_eq!(valid_ids, vec![1, 3]);
+        assert_eq!(report.rejected[0].applicant_id, 2);
+        assert_eq!(report.counts_by_rule().get("age"), Some(&1));
+    }
+
+    #[test]
+    fn test_rules_are_configurable() {
+        let rules = ValidationRules {
+            diastolic_below_systolic: false,
+            ..ValidationRules::default()
+        };
+
+        let mut applicant = applicant();
+        applicant.blood_pressure_dia = applicant.blood_pressure_sys;
+        assert!(rules.validate(&applicant).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_rule_is_rejected() {
+        let json = DEFAULT_VALIDATION_RULES.replace("\"age\"", "\"agee\"");
+        assert!(serde_json::from_str::<ValidationRules>(&json).is_err());
+    }
+}
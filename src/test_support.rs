@@ -0,0 +1,47 @@
+//! Helpers shared by the unit tests
+
+use crate::domain::{Applicant, Gender, SmokerStatus};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Empty scratch directory under the system temp dir, unique to this process
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("premiumcalc_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Minimal formula scripts: a base and final premium plus the outputs the built-in
+/// underwriting rules rate on
+pub fn write_formulas(dir: &Path, final_premium: &str) {
+    for (name, body) in [
+        ("base_premium", "return 100"),
+        ("health_risk_score", "return 1"),
+        ("lifestyle_multiplier", "return 1"),
+        ("final_premium", final_premium),
+    ] {
+        fs::write(dir.join(format!("{}.fc", name)), body).unwrap();
+    }
+}
+
+/// Healthy 35 year old non-smoker; tests change the fields they exercise
+pub fn applicant(id: u32) -> Applicant {
+    Applicant {
+        id,
+        name: format!("Applicant {}", id),
+        age: 35,
+        gender: Gender::Male,
+        smoker: SmokerStatus::NonSmoker,
+        occupation: "Engineer".to_string(),
+        annual_income: 75000.0,
+        bmi: 22.5,
+        blood_pressure_sys: 120,
+        blood_pressure_dia: 80,
+        cholesterol: 180,
+        existing_conditions: Vec::new(),
+        family_history_score: 2,
+        coverage_amount: 500000.0,
+        coverage_years: 20,
+    }
+}
@@ -1,32 +1,110 @@
-use crate::domain::PremiumResult;
+use crate::domain::{CalculationFailure, PremiumResult};
+use crate::services::{PremiumChange, PremiumComparison};
 use ratatui::widgets::ListState;
+use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
+/// One-line notice shown under the results, such as the outcome of a formula reload
+#[derive(Debug, Clone, PartialEq)]
+pub struct Banner {
+    pub message: String,
+    pub is_error: bool,
+}
+
 /// State management for the TUI application
 /// Follows Single Responsibility Principle - manages UI state only
 pub struct AppState {
     pub results: Vec<PremiumResult>,
+    pub failures: Vec<CalculationFailure>,
     pub selected_index: Option<usize>,
     pub selected_expanded: bool,
     pub total_calculation_time: Duration,
     pub list_state: ListState,
+    /// Baseline and candidate version ids when comparing two formula sets
+    pub comparing: Option<(String, String)>,
+    /// Premium change from the baseline formula set by applicant id, when comparing
+    pub changes: HashMap<u32, PremiumChange>,
+    /// Applicants the baseline rated but the candidate could not, so they have no result here
+    pub baseline_only: usize,
+    pub banner: Option<Banner>,
+    /// Sub-factors of the formula set that rated the results, mapped to the factor they feed
+    pub sub_factors: BTreeMap<String, String>,
 }
 
 impl AppState {
-    pub fn new(results: Vec<PremiumResult>, total_time: Duration) -> Self {
+    pub fn new(
+        results: Vec<PremiumResult>,
+        failures: Vec<CalculationFailure>,
+        total_time: Duration,
+    ) -> Self {
         let selected_index = if results.is_empty() { None } else { Some(0) };
         let mut list_state = ListState::default();
         list_state.select(selected_index);
 
         Self {
             results,
+            failures,
             selected_index,
             selected_expanded: false,
             total_calculation_time: total_time,
             list_state,
+            comparing: None,
+            changes: HashMap::new(),
+            baseline_only: 0,
+            banner: None,
+            sub_factors: BTreeMap::new(),
         }
     }
 
+    /// Swap in results from a new calculation, keeping the selected applicant selected
+    /// Falls back to the same position when that applicant has no result any more
+    pub fn replace_results(
+        &mut self,
+        results: Vec<PremiumResult>,
+        failures: Vec<CalculationFailure>,
+        total_time: Duration,
+    ) {
+        let selected_id = self.selected_result().map(|result| result.applicant.id);
+        let position = selected_id
+            .and_then(|id| results.iter().position(|result| result.applicant.id == id))
+            .or_else(|| {
+                self.selected_index
+                    .map(|i| i.min(results.len().saturating_sub(1)))
+            })
+            .filter(|_| !results.is_empty());
+
+        self.results = results;
+        self.failures = failures;
+        self.total_calculation_time = total_time;
+        self.selected_index = position;
+        self.list_state.select(position);
+    }
+
+    /// Show sub-factors under the factor they feed in the detailed view
+    pub fn with_sub_factors(mut self, sub_factors: BTreeMap<String, String>) -> Self {
+        self.sub_factors = sub_factors;
+        self
+    }
+
+    /// Show each result next to its premium under the comparison's baseline formula set
+    pub fn with_comparison(mut self, comparison: &PremiumComparison) -> Self {
+        self.comparing = Some((
+            comparison.baseline_version.clone(),
+            comparison.candidate_version.clone(),
+        ));
+        self.changes = comparison
+            .changes
+            .iter()
+            .map(|change| (change.applicant_id, change.clone()))
+            .collect();
+        self.baseline_only = comparison.baseline_only.len();
+        self
+    }
+
+    pub fn change(&self, result: &PremiumResult) -> Option<&PremiumChange> {
+        self.changes.get(&result.applicant.id)
+    }
+
     pub fn select_next(&mut self) {
         if self.results.is_empty() {
             return;
@@ -101,10 +179,11 @@ impl AppState {
     }
 
     pub fn average_calculation_time_ms(&self) -> f64 {
-        if self.results.is_empty() {
+        let calculated = self.results.len() + self.failures.len();
+        if calculated == 0 {
             0.0
         } else {
-            self.total_calculation_time.as_secs_f64() * 1000.0 / self.results.len() as f64
+            self.total_calculation_time.as_secs_f64() * 1000.0 / calculated as f64
         }
     }
 
@@ -2,5 +2,6 @@ mod app_state;
 mod renderer;
 mod ui_renderer;
 
-pub use app_state::AppState;
+pub use app_state::{AppState, Banner};
+pub use renderer::{factor_breakdown, formula_label};
 pub use ui_renderer::render;
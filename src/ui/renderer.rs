@@ -1,12 +1,13 @@
-use crate::domain::PremiumResult;
+use crate::domain::{Decision, PaymentMode, PremiumResult};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
 };
+use std::collections::BTreeMap;
 
 /// Renders the summary view for a premium result
 pub fn render_summary(result: &PremiumResult) -> Vec<Line<'_>> {
-    vec![
+    let mut lines = vec![
         Line::from(vec![
             Span::styled("Name: ", Style::default().fg(Color::Cyan)),
             Span::raw(&result.applicant.name),
@@ -17,7 +18,11 @@ pub fn render_summary(result: &PremiumResult) -> Vec<Line<'_>> {
             Span::styled("Gender: ", Style::default().fg(Color::Cyan)),
             Span::raw(format!("{} | ", result.applicant.gender)),
             Span::styled("Smoker: ", Style::default().fg(Color::Cyan)),
-            Span::raw(if result.applicant.smoker { "Yes" } else { "No" }),
+            Span::raw(if result.applicant.smoker.is_smoker() {
+                "Yes"
+            } else {
+                "No"
+            }),
         ]),
         Line::from(""),
         Line::from(vec![
@@ -30,18 +35,22 @@ pub fn render_summary(result: &PremiumResult) -> Vec<Line<'_>> {
         Line::from(""),
         Line::from(vec![
             Span::styled(
-                "Monthly Premium: ",
+                "Monthly Payment: ",
                 Style::default()
                     .fg(Color::Green)
                     .add_modifier(Modifier::BOLD),
             ),
             Span::styled(
-                format!("${:.2}", result.final_premium),
+                format!("${:.2}", result.installment(PaymentMode::Monthly)),
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD),
             ),
         ]),
+        Line::from(vec![
+            Span::styled("Decision: ", Style::default().fg(Color::Cyan)),
+            decision_span(result.underwriting.decision),
+        ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("Calculation Time: ", Style::default().fg(Color::Cyan)),
@@ -57,12 +66,17 @@ pub fn render_summary(result: &PremiumResult) -> Vec<Line<'_>> {
                 .fg(Color::Gray)
                 .add_modifier(Modifier::ITALIC),
         )),
-    ]
+    ];
+    lines.extend(render_warnings(result));
+    lines
 }
 
 /// Renders the expanded/detailed view for a premium result
-pub fn render_detailed(result: &PremiumResult) -> Vec<Line<'_>> {
-    vec![
+pub fn render_detailed<'a>(
+    result: &'a PremiumResult,
+    sub_factors: &BTreeMap<String, String>,
+) -> Vec<Line<'a>> {
+    let mut lines = vec![
         Line::from(vec![Span::styled(
             "━━━ APPLICANT INFO ━━━",
             Style::default()
@@ -102,15 +116,25 @@ pub fn render_detailed(result: &PremiumResult) -> Vec<Line<'_>> {
         )),
         Line::from(format!(
             "Existing Conditions: {}",
-            if result.applicant.existing_conditions == "none" {
-                "None"
+            if result.applicant.existing_conditions.is_empty() {
+                "None".to_string()
             } else {
-                &result.applicant.existing_conditions
+                result
+                    .applicant
+                    .existing_conditions
+                    .iter()
+                    .map(|condition| condition.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
             }
         )),
         Line::from(format!(
             "Smoker: {} | Family History Score: {}/6",
-            if result.applicant.smoker { "Yes" } else { "No" },
+            if result.applicant.smoker.is_smoker() {
+                "Yes"
+            } else {
+                "No"
+            },
             result.applicant.family_history_score
         )),
         Line::from(""),
@@ -120,27 +144,18 @@ pub fn render_detailed(result: &PremiumResult) -> Vec<Line<'_>> {
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
         )]),
+        Line::from(format!("Plan Version:          {}", result.plan_version)),
         Line::from(format!(
             "Base Premium:          ${:.2}",
             result.base_premium
         )),
-        Line::from(format!("Age Factor:            x{:.2}", result.age_factor)),
-        Line::from(format!(
-            "Health Risk Score:     x{:.3}",
-            result.health_risk_score
-        )),
-        Line::from(format!(
-            "Lifestyle Multiplier:  x{:.2}",
-            result.lifestyle_multiplier
-        )),
-        Line::from(format!(
-            "Occupation Factor:     x{:.2}",
-            result.occupation_factor
-        )),
+    ];
+    lines.extend(render_formula_outputs(result, sub_factors));
+    lines.extend(vec![
         Line::from(""),
         Line::from(vec![
             Span::styled(
-                "MONTHLY PREMIUM:       ",
+                "RATED PREMIUM:         ",
                 Style::default()
                     .fg(Color::Green)
                     .add_modifier(Modifier::BOLD),
@@ -151,7 +166,44 @@ pub fn render_detailed(result: &PremiumResult) -> Vec<Line<'_>> {
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD),
             ),
+            Span::styled("/mo before modal factors", Style::default().fg(Color::Gray)),
         ]),
+    ]);
+    lines.extend(render_payment_options(result));
+    lines.extend(render_trace(result));
+    lines.extend(vec![
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "━━━ UNDERWRITING ━━━",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(vec![
+            Span::raw("Decision: "),
+            decision_span(result.underwriting.decision),
+        ]),
+        Line::from(vec![
+            Span::raw("Max Coverage: "),
+            limit_span(
+                result.underwriting.affordability.max_coverage,
+                result.underwriting.affordability.coverage_exceeded,
+            ),
+            Span::raw(" | Max Annual Premium: "),
+            limit_span(
+                result.underwriting.affordability.max_annual_premium,
+                result.underwriting.affordability.premium_exceeded,
+            ),
+        ]),
+    ]);
+    lines.extend(
+        result
+            .underwriting
+            .reasons
+            .iter()
+            .map(|reason| Line::from(format!("  • {}", reason))),
+    );
+    lines.extend(vec![
         Line::from(""),
         Line::from(vec![
             Span::styled("Calculation Time: ", Style::default().fg(Color::Gray)),
@@ -160,5 +212,180 @@ pub fn render_detailed(result: &PremiumResult) -> Vec<Line<'_>> {
                 Style::default().fg(Color::Magenta),
             ),
         ]),
-    ]
+    ]);
+    lines.extend(render_warnings(result));
+    lines
+}
+
+/// Renders every intermediate formula output as a multiplier line, sub-factors under their factor
+fn render_formula_outputs<'a>(
+    result: &'a PremiumResult,
+    sub_factors: &BTreeMap<String, String>,
+) -> Vec<Line<'a>> {
+    factor_breakdown(result, sub_factors)
+        .into_iter()
+        .map(|(label, value)| Line::from(format!("{:<23}x{:.3}", format!("{}:", label), value)))
+        .collect()
+}
+
+/// Labelled multipliers between the base and the final premium, in rating order
+/// Each sub-factor follows the factor it feeds, indented, so the unindented lines multiply out
+/// to the premium
+pub fn factor_breakdown(
+    result: &PremiumResult,
+    sub_factors: &BTreeMap<String, String>,
+) -> Vec<(String, f64)> {
+    let factors: Vec<(&str, f64)> = result
+        .outputs
+        .iter()
+        .filter(|(name, _)| *name != "base_premium" && *name != "final_premium")
+        .collect();
+    let parent = |name: &str| {
+        sub_factors
+            .get(name)
+            .filter(|parent| factors.iter().any(|(other, _)| other == parent))
+    };
+
+    let mut rows = Vec::with_capacity(factors.len());
+    let mut pending: Vec<(&str, f64, usize)> = factors
+        .iter()
+        .rev()
+        .filter(|(name, _)| parent(name).is_none())
+        .map(|&(name, value)| (name, value, 0))
+        .collect();
+    while let Some((name, value, depth)) = pending.pop() {
+        let label = match depth {
+            0 => formula_label(name),
+            _ => format!("{}└ {}", "  ".repeat(depth), formula_label(name)),
+        };
+        rows.push((label, value));
+        pending.extend(
+            factors
+                .iter()
+                .rev()
+                .filter(|(child, _)| parent(child).is_some_and(|p| p == name))
+                .map(|&(child, value)| (child, value, depth + 1)),
+        );
+    }
+    rows
+}
+
+/// Renders what billing charges in each payment mode
+fn render_payment_options(result: &PremiumResult) -> Vec<Line<'_>> {
+    if result.payment_options.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "━━━ PAYMENT OPTIONS ━━━",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )]),
+    ];
+    lines.extend(result.payment_options.iter().map(|option| {
+        Line::from(format!(
+            "{:<12}{:>2} x ${:<10.2} = ${:.2}/yr",
+            option.mode.label(),
+            option.mode.installments_per_year(),
+            option.installment,
+            option.annual_total
+        ))
+    }));
+    lines
+}
+
+/// Renders how each formula reached its output, when the result was rated in explain mode
+fn render_trace(result: &PremiumResult) -> Vec<Line<'_>> {
+    if result.trace.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "━━━ EXPLANATION ━━━",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )]),
+    ];
+    for trace in &result.trace {
+        let mut spans = vec![Span::raw(format!(
+            "{:<23}{}",
+            format!("{}:", formula_label(&trace.formula)),
+            trace.output
+        ))];
+        if let Some(branch) = trace.branch_label() {
+            spans.push(Span::styled(
+                format!("  when {}", branch),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        lines.push(Line::from(spans));
+        if !trace.inputs.is_empty() {
+            lines.push(Line::from(Span::styled(
+                format!("  {}", trace.inputs_label()),
+                Style::default().fg(Color::Gray),
+            )));
+        }
+    }
+    lines
+}
+
+/// Renders an underwriting decision, coloured by severity
+fn decision_span(decision: Decision) -> Span<'static> {
+    let color = match decision {
+        Decision::Standard => Color::Green,
+        Decision::Rated => Color::Yellow,
+        Decision::Refer => Color::Magenta,
+        Decision::Decline => Color::Red,
+    };
+    Span::styled(
+        decision.as_str().to_uppercase(),
+        Style::default().fg(color).add_modifier(Modifier::BOLD),
+    )
+}
+
+/// Renders an income-based limit, in red when the quote exceeds it
+fn limit_span(limit: f64, exceeded: bool) -> Span<'static> {
+    let style = if exceeded {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    Span::styled(format!("${:.0}", limit), style)
+}
+
+/// Turns a formula name like `family_history_factor` into `Family History Factor`
+pub fn formula_label(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Renders the warnings raised while rating, if any
+fn render_warnings(result: &PremiumResult) -> Vec<Line<'_>> {
+    if result.warnings.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines = vec![Line::from("")];
+    lines.extend(result.warnings.iter().map(|warning| {
+        Line::from(Span::styled(
+            format!("⚠ {}", warning),
+            Style::default().fg(Color::Yellow),
+        ))
+    }));
+    lines
 }
@@ -1,3 +1,4 @@
+use crate::domain::PaymentMode;
 use crate::ui::{app_state::AppState, renderer};
 use ratatui::{
     layout::{Constraint, Direction, Layout},
@@ -10,22 +11,46 @@ use ratatui::{
 /// Renders the complete UI
 /// Follows Single Responsibility Principle - only handles UI rendering
 pub fn render(f: &mut Frame, state: &mut AppState) {
+    let banner_height = if state.banner.is_some() { 3 } else { 0 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
             Constraint::Min(0),
+            Constraint::Length(banner_height),
             Constraint::Length(3),
         ])
         .split(f.area());
 
     render_header(f, chunks[0], state);
     render_main_content(f, chunks[1], state);
-    render_footer(f, chunks[2]);
+    render_banner(f, chunks[2], state);
+    render_footer(f, chunks[3]);
+}
+
+fn render_banner(f: &mut Frame, area: ratatui::layout::Rect, state: &AppState) {
+    let Some(banner) = &state.banner else {
+        return;
+    };
+    let color = if banner.is_error {
+        Color::Red
+    } else {
+        Color::Green
+    };
+
+    let paragraph = Paragraph::new(banner.message.as_str())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(color)),
+        )
+        .style(Style::default().fg(color));
+
+    f.render_widget(paragraph, area);
 }
 
 fn render_header(f: &mut Frame, area: ratatui::layout::Rect, state: &AppState) {
-    let header_text = vec![Line::from(vec![
+    let mut header_spans = vec![
         Span::styled(
             "Premium Calculator ",
             Style::default()
@@ -44,7 +69,29 @@ fn render_header(f: &mut Frame, area: ratatui::layout::Rect, state: &AppState) {
             " | Avg: {:.2}ms",
             state.average_calculation_time_ms()
         )),
-    ])];
+    ];
+    if let Some((baseline, candidate)) = &state.comparing {
+        header_spans.push(Span::raw(" | "));
+        header_spans.push(Span::styled(
+            format!("Comparing {} → {}", baseline, candidate),
+            Style::default().fg(Color::Yellow),
+        ));
+        if state.baseline_only > 0 {
+            header_spans.push(Span::raw(" | "));
+            header_spans.push(Span::styled(
+                format!("{} only rated by baseline", state.baseline_only),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
+    }
+    if !state.failures.is_empty() {
+        header_spans.push(Span::raw(" | "));
+        header_spans.push(Span::styled(
+            format!("{} failed", state.failures.len()),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    let header_text = vec![Line::from(header_spans)];
 
     let header = Paragraph::new(header_text).block(
         Block::default()
@@ -71,13 +118,25 @@ fn render_applicant_list(f: &mut Frame, area: ratatui::layout::Rect, state: &mut
         .iter()
         .enumerate()
         .map(|(i, result)| {
-            let content = format!(
-                "{:2}. {} - ${:.2}/mo ({:.2}ms)",
-                i + 1,
-                result.applicant.name,
-                result.final_premium,
-                result.calculation_time_ms
-            );
+            let content = match state.change(result) {
+                Some(change) => format!(
+                    "{:2}. {} - ${:.2} → ${:.2}/mo ({})",
+                    i + 1,
+                    result.applicant.name,
+                    change.baseline,
+                    change.candidate,
+                    change
+                        .percent_change()
+                        .map_or("n/a".to_string(), |percent| format!("{:+.1}%", percent))
+                ),
+                None => format!(
+                    "{:2}. {} - ${:.2}/mo ({:.2}ms)",
+                    i + 1,
+                    result.applicant.name,
+                    result.installment(PaymentMode::Monthly),
+                    result.calculation_time_ms
+                ),
+            };
 
             let style = if state.selected_index == Some(i) {
                 Style::default()
@@ -105,7 +164,7 @@ fn render_applicant_list(f: &mut Frame, area: ratatui::layout::Rect, state: &mut
 fn render_detail_panel(f: &mut Frame, area: ratatui::layout::Rect, state: &AppState) {
     if let Some(result) = state.selected_result() {
         let detail_text = if state.selected_expanded {
-            renderer::render_detailed(result)
+            renderer::render_detailed(result, &state.sub_factors)
         } else {
             renderer::render_summary(result)
         };